@@ -0,0 +1,150 @@
+use std::future;
+use std::io;
+use std::pin::Pin;
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+use crate::encode::{encode_end, encode_header, encode_pixel, read_pixel};
+use crate::error::Error;
+use crate::meta::ImageMeta;
+use crate::state::State;
+use crate::streaming::StreamingDecoder;
+
+// Number of bytes read or buffered before writing at a time.
+const ASYNC_BUFFER_LEN: usize = 8 * 1024;
+
+/// Asynchronously decodes a QOI encoded image, like `decode_image`. The
+/// decoded pixel data is written to the provided `writer`, and upon success,
+/// the image's `ImageMeta` data is returned.
+/// 
+/// The image is read in chunks and decoded with a `StreamingDecoder`, so
+/// the returned future yields whenever the `reader` or `writer` isn't ready,
+/// and never blocks. Reading stops once the image's end marker has been
+/// read, or at the end of the `reader`.
+/// 
+/// This function accepts the `futures-io` traits. Tokio types can be adapted
+/// with the `compat` module of the `tokio-util` crate.
+/// 
+/// Only available with the `async` feature.
+pub async fn decode_image_async<R, W>(mut reader: R, mut writer: W) -> Result<ImageMeta, Error>
+where
+  R: AsyncRead + Unpin,
+  W: AsyncWrite + Unpin,
+{
+  let mut decoder = StreamingDecoder::new();
+  let mut buf = vec![0; ASYNC_BUFFER_LEN];
+
+  while !decoder.is_complete() {
+    let read = read(&mut reader, &mut buf).await?;
+
+    if read == 0 {
+      break;
+    }
+
+    write_all(&mut writer, decoder.feed(&buf[..read])?).await?;
+  }
+
+  flush(&mut writer).await?;
+
+  decoder.finish().map(|report| report.meta)
+}
+
+/// Asynchronously encodes an image's raw pixel data and `ImageMeta` data
+/// into a QOI encoded image, like `encode_image`.
+/// 
+/// Pixel data is read and encoded in chunks, so the returned future yields
+/// whenever the `reader` or `writer` isn't ready, and never blocks.
+/// `Error::UnexpectedEof` is returned if the `reader` ends before all pixels
+/// have been read.
+/// 
+/// This function accepts the `futures-io` traits. Tokio types can be adapted
+/// with the `compat` module of the `tokio-util` crate.
+/// 
+/// Only available with the `async` feature.
+pub async fn encode_image_async<R, W>(
+  mut reader: R,
+  mut writer: W,
+  meta: &ImageMeta,
+) -> Result<(), Error>
+where
+  R: AsyncRead + Unpin,
+  W: AsyncWrite + Unpin,
+{
+  let mut encoded = Vec::with_capacity(ASYNC_BUFFER_LEN);
+  encode_header(meta, &mut encoded)?;
+
+  let pixel_len = meta.channels as usize;
+  let mut state = State::new();
+  let mut buf = vec![0; ASYNC_BUFFER_LEN];
+  let mut buf_len = 0;
+  let mut pixels_remaining = meta.num_pixels();
+
+  while pixels_remaining > 0 {
+    // Never read past the pixel data, so anything that follows it is left
+    // unread for the caller.
+    let bytes_remaining = pixels_remaining * pixel_len as u64 - buf_len as u64;
+    let read_len = ((buf.len() - buf_len) as u64).min(bytes_remaining) as usize;
+    let read = read(&mut reader, &mut buf[buf_len..buf_len + read_len]).await?;
+
+    if read == 0 {
+      return Err(Error::UnexpectedEof(None));
+    }
+
+    buf_len += read;
+
+    let mut pixels = buf[..buf_len].chunks_exact(pixel_len);
+
+    for pixel_buf in pixels.by_ref() {
+      let pixel = read_pixel(&state, pixel_buf);
+
+      encode_pixel(&mut state, pixel, &mut encoded)?;
+      state.prev_pixel = pixel;
+      pixels_remaining -= 1;
+    }
+
+    // Carry over a pixel that was split across reads.
+    let remainder = pixels.remainder().len();
+    buf.copy_within(buf_len - remainder..buf_len, 0);
+    buf_len = remainder;
+
+    if encoded.len() >= ASYNC_BUFFER_LEN {
+      write_all(&mut writer, &encoded).await?;
+      encoded.clear();
+    }
+  }
+
+  encode_end(&mut state, &mut encoded)?;
+  write_all(&mut writer, &encoded).await?;
+  flush(&mut writer).await?;
+
+  Ok(())
+}
+
+// Reads bytes from the reader into `buf`, returning the number of bytes read.
+async fn read<R: AsyncRead + Unpin>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+  loop {
+    match future::poll_fn(|cx| Pin::new(&mut *reader).poll_read(cx, buf)).await {
+      Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+      result => return result,
+    }
+  }
+}
+
+// Writes all of `buf` to the writer.
+async fn write_all<W: AsyncWrite + Unpin>(writer: &mut W, mut buf: &[u8]) -> io::Result<()> {
+  while !buf.is_empty() {
+    match future::poll_fn(|cx| Pin::new(&mut *writer).poll_write(cx, buf)).await {
+      Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+      Ok(written) => buf = &buf[written..],
+      Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+      Err(e) => return Err(e),
+    }
+  }
+
+  Ok(())
+}
+
+// Flushes the writer.
+async fn flush<W: AsyncWrite + Unpin>(writer: &mut W) -> io::Result<()> {
+  future::poll_fn(|cx| Pin::new(&mut *writer).poll_flush(cx)).await
+}
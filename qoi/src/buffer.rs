@@ -0,0 +1,141 @@
+use std::{io, mem};
+
+// Number of bytes read from the underlying reader at a time.
+const READ_BUFFER_LEN: usize = 8 * 1024;
+
+// Number of bytes buffered before writing to the underlying writer.
+const WRITE_BUFFER_LEN: usize = 8 * 1024;
+
+// A minimal buffered reader that allows decoding directly from in-memory
+// slices of an `io::Read` source. Unlike `std::io::BufReader`, it guarantees
+// that a requested minimum number of bytes are contiguous in the buffer,
+// which allows an `Op` to be decoded even if it was split across reads.
+pub struct ReadBuffer<R> {
+  buf: Box<[u8]>,
+  len: usize,
+  pos: usize,
+  reader: R,
+}
+
+impl<R: io::Read> ReadBuffer<R> {
+  pub fn new(reader: R) -> Self {
+    Self {
+      buf: vec![0; READ_BUFFER_LEN].into_boxed_slice(),
+      len: 0,
+      pos: 0,
+      reader,
+    }
+  }
+
+  // Returns the buffered bytes that haven't been consumed yet. If fewer than
+  // `min` bytes are buffered, more are read from the underlying reader
+  // first. Fewer than `min` bytes are only returned once the end of the
+  // reader has been reached.
+  #[inline]
+  pub fn fill(&mut self, min: usize) -> Result<&[u8], io::Error> {
+    if self.len - self.pos < min {
+      self.buf.copy_within(self.pos..self.len, 0);
+      self.len -= self.pos;
+      self.pos = 0;
+
+      while self.len < min {
+        match self.reader.read(&mut self.buf[self.len..]) {
+          Ok(0) => break,
+          Ok(read) => self.len += read,
+          Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+          Err(e) => return Err(e),
+        }
+      }
+    }
+
+    Ok(&self.buf[self.pos..self.len])
+  }
+
+  // Marks the given number of buffered bytes as consumed.
+  #[inline]
+  pub fn consume(&mut self, amount: usize) {
+    self.pos = (self.pos + amount).min(self.len);
+  }
+}
+
+// A minimal buffered writer that batches the small writes made while
+// decoding and encoding, so unbuffered writers aren't written to for every
+// pixel or `Op`. Unlike `std::io::BufWriter`, it hands out slices of its
+// buffer to be written into directly, which allows decoded pixels to be
+// converted in place. Buffered bytes are written by `flush`, or once the
+// buffer is full. Like `std::io::BufWriter`, it also writes them when
+// dropped, ignoring errors, so everything produced before an error is still
+// written, as it would be without buffering.
+pub struct WriteBuffer<W: io::Write> {
+  buf: Box<[u8]>,
+  len: usize,
+  writer: W,
+}
+
+impl<W: io::Write> WriteBuffer<W> {
+  pub fn new(writer: W) -> Self {
+    Self {
+      buf: vec![0; WRITE_BUFFER_LEN].into_boxed_slice(),
+      len: 0,
+      writer,
+    }
+  }
+
+  // Returns the next `len` bytes of the buffer to be written into, which must
+  // be at most the buffer's length. If there isn't enough space, the
+  // buffered bytes are written to the underlying writer first.
+  #[inline]
+  pub fn reserve(&mut self, len: usize) -> Result<&mut [u8], io::Error> {
+    if self.buf.len() - self.len < len {
+      self.write_buf()?;
+    }
+
+    let start = self.len;
+    self.len += len;
+
+    Ok(&mut self.buf[start..self.len])
+  }
+
+  // Buffers all of the given bytes. Bytes that don't fit in the buffer are
+  // written to the underlying writer directly.
+  #[inline]
+  pub fn write_all(&mut self, bytes: &[u8]) -> Result<(), io::Error> {
+    if self.buf.len() - self.len < bytes.len() {
+      self.write_buf()?;
+
+      if bytes.len() >= self.buf.len() {
+        return self.writer.write_all(bytes);
+      }
+    }
+
+    self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+    self.len += bytes.len();
+
+    Ok(())
+  }
+
+  // Writes the buffered bytes to the underlying writer, and flushes it.
+  pub fn flush(&mut self) -> Result<(), io::Error> {
+    self.write_buf()?;
+    self.writer.flush()
+  }
+
+  // Writes the buffered bytes to the underlying writer. They're taken out of
+  // the buffer first, so they're never written twice, even if writing them
+  // fails partway through.
+  fn write_buf(&mut self) -> Result<(), io::Error> {
+    let len = mem::replace(&mut self.len, 0);
+
+    if len > 0 {
+      self.writer.write_all(&self.buf[..len])?;
+    }
+
+    Ok(())
+  }
+}
+
+impl<W: io::Write> Drop for WriteBuffer<W> {
+  fn drop(&mut self) {
+    let _ = self.write_buf();
+  }
+}
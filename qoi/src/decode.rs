@@ -1,10 +1,37 @@
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+use core::mem;
+#[cfg(feature = "std")]
 use std::io;
 
-use crate::error::Error;
-use crate::meta::{Colorspace, ImageMeta, QOI_BYTES_MAGIC, QOI_HEADER_LEN};
+#[cfg(feature = "std")]
+use crate::buffer::WriteBuffer;
+#[cfg(feature = "std")]
+use crate::decoder::{Decoder, Pixels};
+use crate::error::{Error, ErrorLocation};
+use crate::format::{Output, PixelFormat};
+use crate::meta::{
+  decoded_len,
+  validate_channels,
+  Colorspace,
+  ImageMeta,
+  QOI_BYTES_END,
+  QOI_BYTES_MAGIC,
+  QOI_HEADER_LEN,
+};
+#[cfg(feature = "alloc")]
+use crate::meta::QOI_MAX_RUN;
 use crate::op::Op;
+use crate::options::{DecodeOptions, Recovery, SupportedOptions};
 use crate::pixel::{Pixel, PixelDiff};
+use crate::progress::ProgressTracker;
+#[cfg(feature = "std")]
+use crate::rect::Rect;
+use crate::report::{DecodeReport, EndMarker};
+use crate::srgb::{srgb_to_linear, unorm_to_f32};
 use crate::state::State;
+#[cfg(feature = "std")]
+use crate::stats::{DecodeStats, Observer};
 
 /// Decodes a QOI encoded image. The decoded pixel data is written to the
 /// provided `writer`, and upon success, the image's `ImageMeta` data is
@@ -15,32 +42,304 @@ use crate::state::State;
 /// encoded image source, and `std::io::Write` for the decoded pixel data
 /// destination.
 /// 
-/// Note that this function performs frequent reads and writes, so it's
-/// recommended to provide a buffered IO implementation such as
-/// `std::io::BufReader` and `std::io::BufWriter` for streaming applications.
+/// The encoded image is read in chunks, so the `reader` may be read past the
+/// end of the image. Likewise, the decoded pixel data is written in chunks,
+/// so there's no need to provide a buffered IO implementation such as
+/// `std::io::BufReader` or `std::io::BufWriter`. Pixels decoded before an
+/// error is encountered are still written to the `writer`, as far as
+/// possible. If the image is already in memory, prefer `decode_to_slice` or
+/// `decode_to_vec`.
+#[cfg(feature = "std")]
 pub fn decode_image<R: io::Read, W: io::Write>(
-  mut reader: R,
-  mut writer: W,
+  reader: R,
+  writer: W,
 ) -> Result<ImageMeta, Error> {
-  let meta = decode_header(&mut reader)?;
-  let mut state = State::new();
-  let mut bytes = reader.bytes();
+  decode_image_with(reader, writer, &DecodeOptions::default()).map(|report| report.meta)
+}
+
+/// Decodes a QOI encoded image like `decode_image`, but customized by the
+/// given `options`. For example, to decode pixels in a specific
+/// `PixelFormat`.
+/// 
+/// Upon success, a `DecodeReport` is returned. Its `ImageMeta` always
+/// describes the image as found in its header, regardless of the options
+/// provided.
+/// 
+/// The image's header is checked against `options.limits` before any pixels
+/// are decoded.
+/// 
+/// As with `decode_image`, the `reader` is read in chunks, so a reader
+/// that's shared with other data, such as a `File` holding several images,
+/// may be left advanced past the end of the image, even in lenient mode.
+/// Pass a slice or an in-memory cursor holding only the image when the
+/// reader's position matters.
+#[cfg(feature = "std")]
+pub fn decode_image_with<R: io::Read, W: io::Write>(
+  reader: R,
+  writer: W,
+  options: &DecodeOptions,
+) -> Result<DecodeReport, Error> {
+  options.check_supported(
+    SupportedOptions::BACKGROUND
+      | SupportedOptions::FORMAT
+      | SupportedOptions::PREMULTIPLY
+      | SupportedOptions::PROGRESS
+      | SupportedOptions::RECOVERY
+      | SupportedOptions::STRICT
+  )?;
+
+  decode_image_observed(reader, writer, options, &mut ())
+}
 
-  for _ in 0..meta.num_pixels() {
-    let pixel = decode_pixel(&mut state, &mut bytes)?;
+/// Decodes a QOI encoded image like `decode_image`, while collecting
+/// `DecodeStats` about the ops the image is encoded with, such as how many
+/// of each kind there are. Upon success, the image's `ImageMeta` data is
+/// returned along with the stats.
+/// 
+/// Collecting stats is opt-in, the other decode functions don't pay for it.
+/// To only collect stats, write the decoded pixel data to `std::io::sink`.
+/// 
+/// ```rust
+/// use qoi::decode_image_with_stats;
+/// 
+/// let image = include_bytes!("../tests/testcard_rgba_256x256.qoi");
+/// let (meta, stats) = decode_image_with_stats(image.as_slice(), std::io::sink())
+///   .expect("Failed to decode image");
+/// 
+/// println!("{} index ops, {:.0}% index hits", stats.index.count, stats.index_hit_ratio() * 100.0);
+/// ```
+#[cfg(feature = "std")]
+pub fn decode_image_with_stats<R: io::Read, W: io::Write>(
+  reader: R,
+  writer: W,
+) -> Result<(ImageMeta, DecodeStats), Error> {
+  let mut stats = DecodeStats::default();
+  let report = decode_image_observed(reader, writer, &DecodeOptions::default(), &mut stats)?;
 
-    if pixel != state.prev_pixel {
-      state.cache_insert(pixel);
-      state.prev_pixel = pixel;
+  Ok((report.meta, stats))
+}
+
+// Decodes a QOI encoded image like `decode_image_with`, passing each decoded
+// `Op` to the given observer.
+#[cfg(feature = "std")]
+fn decode_image_observed<R: io::Read, W: io::Write, O: Observer>(
+  reader: R,
+  writer: W,
+  options: &DecodeOptions,
+  observer: &mut O,
+) -> Result<DecodeReport, Error> {
+  let mut decoder = Decoder::with_options_unchecked(reader, options)?;
+  let output = *decoder.output();
+  let pixel_len = output.format.bytes_per_pixel();
+  let mut progress = ProgressTracker::new(options.progress, decoder.meta());
+  let mut pixels_done = 0;
+  let mut writer = WriteBuffer::new(writer);
+
+  while let Some(pixel) = decoder.next_pixel_observed(observer)? {
+    output.write_pixel(pixel, writer.reserve(pixel_len)?);
+    pixels_done += 1;
+    progress.update(pixels_done)?;
+  }
+
+  writer.flush()?;
+
+  decoder.finish()
+}
+
+/// Decodes a QOI encoded image like `decode_image_with`, into a `writer`
+/// that can seek. This allows rows to be written out of order, so when
+/// `DecodeOptions::flip_vertically` is `true`, the image's rows are written
+/// bottom-up, starting from the writer's current position. The writer is
+/// left positioned at the end of the decoded pixel data.
+/// 
+/// When recovering a truncated image with `Recovery::Truncate`, only the
+/// pixels decoded before the error are written.
+#[cfg(feature = "std")]
+pub fn decode_image_seekable<R: io::Read, W: io::Write + io::Seek>(
+  reader: R,
+  mut writer: W,
+  options: &DecodeOptions,
+) -> Result<DecodeReport, Error> {
+  if !options.flip_vertically {
+    return decode_image_with(reader, writer, options);
+  }
+
+  options.check_supported(
+    SupportedOptions::BACKGROUND
+      | SupportedOptions::FLIP_VERTICALLY
+      | SupportedOptions::FORMAT
+      | SupportedOptions::PREMULTIPLY
+      | SupportedOptions::PROGRESS
+      | SupportedOptions::RECOVERY
+      | SupportedOptions::STRICT
+  )?;
+
+  let mut decoder = Decoder::with_options_unchecked(reader, options)?;
+  let output = *decoder.output();
+  let pixel_len = output.format.bytes_per_pixel();
+  let row_len = decoder.row_len();
+  let start = writer.stream_position()?;
+  let mut row = vec![0; row_len];
+  let mut progress = ProgressTracker::new(options.progress, decoder.meta());
+  let mut pixels_done = 0;
+  let mut y = decoder.meta().height as u64;
+  let mut x = 0;
+
+  let mut write_row = |y: u64, row: &[u8]| -> Result<(), Error> {
+    writer.seek(io::SeekFrom::Start(start + y * row_len as u64))?;
+    writer.write_all(row)?;
+    Ok(())
+  };
+
+  while let Some(pixel) = decoder.next_pixel()? {
+    output.write_pixel(pixel, &mut row[x..]);
+    x += pixel_len;
+
+    if x == row_len {
+      y -= 1;
+      write_row(y, &row)?;
+      x = 0;
     }
 
-    match meta.colorspace {
-      Colorspace::Linear => {
-        writer.write_all(&[pixel.r, pixel.g, pixel.b])?;
+    pixels_done += 1;
+    progress.update(pixels_done)?;
+  }
+
+  if x > 0 {
+    write_row(y - 1, &row[..x])?;
+  }
+
+  let end = start + decoder.meta().height as u64 * row_len as u64;
+  writer.seek(io::SeekFrom::Start(end))?;
+  writer.flush()?;
+
+  decoder.finish()
+}
+
+/// Lazily decodes a QOI encoded image pixel by pixel. The image's header is
+/// decoded up front, and upon success, the image's `ImageMeta` data is
+/// returned along with an iterator over its pixels, see `Pixels`.
+/// 
+/// ```rust
+/// use qoi::decode_pixels;
+/// 
+/// let image = include_bytes!("../tests/testcard_rgba_256x256.qoi");
+/// let (meta, pixels) = decode_pixels(image.as_slice()).expect("Failed to decode header");
+/// 
+/// let mut opaque = 0;
+/// 
+/// for pixel in pixels {
+///   if pixel.expect("Failed to decode pixel").a == 255 {
+///     opaque += 1;
+///   }
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub fn decode_pixels<R: io::Read>(reader: R) -> Result<(ImageMeta, Pixels<R>), Error> {
+  decode_pixels_with(reader, &DecodeOptions::default())
+}
+
+/// Lazily decodes a QOI encoded image pixel by pixel like `decode_pixels`,
+/// customized by the given `options`. The image's header is checked against
+/// `options.limits` before any pixels are decoded.
+/// 
+/// Pixels are yielded as is, so only the `recovery` option is supported,
+/// `Error::UnsupportedOption` is returned for any other option.
+#[cfg(feature = "std")]
+pub fn decode_pixels_with<R: io::Read>(
+  reader: R,
+  options: &DecodeOptions,
+) -> Result<(ImageMeta, Pixels<R>), Error> {
+  options.check_supported(SupportedOptions::RECOVERY)?;
+
+  let decoder = Decoder::with_options_unchecked(reader, options)?;
+  let meta = *decoder.meta();
+
+  Ok((meta, decoder.into_pixels()))
+}
+
+/// Decodes only the pixels of a QOI encoded image within the given `rect`,
+/// writing them row by row to the provided `writer`. Upon success, the
+/// image's `ImageMeta` data is returned.
+/// 
+/// Pixels are written as RGB or RGBA to match the image's header. Every
+/// pixel up to the end of the rectangle still has to be decoded, but nothing
+/// past it is, and reading stops once the rectangle's last row is done, so the
+/// rest of the image is skipped entirely. The `reader` may still be read
+/// slightly past that point, since it's read in chunks.
+/// 
+/// `Error::InvalidRect` is returned if the rectangle isn't entirely within
+/// the image.
+/// 
+/// ```rust
+/// use qoi::{decode_region, Rect};
+/// 
+/// let image = include_bytes!("../tests/testcard_rgba_256x256.qoi");
+/// let rect = Rect { x: 64, y: 64, width: 32, height: 16 };
+/// let mut tile = Vec::new();
+/// 
+/// decode_region(image.as_slice(), &mut tile, rect).expect("Failed to decode region");
+/// assert_eq!(tile.len(), 32 * 16 * 4);
+/// ```
+#[cfg(feature = "std")]
+pub fn decode_region<R: io::Read, W: io::Write>(
+  reader: R,
+  writer: W,
+  rect: Rect,
+) -> Result<ImageMeta, Error> {
+  decode_region_with(reader, writer, rect, &DecodeOptions::default())
+}
+
+/// Decodes only the pixels of a QOI encoded image within the given `rect`
+/// like `decode_region`, customized by the given `options`. The image's
+/// header is checked against `options.limits` before any pixels are decoded.
+/// 
+/// Only the `background`, `format`, and `premultiply` options are supported,
+/// `Error::UnsupportedOption` is returned for any other option.
+#[cfg(feature = "std")]
+pub fn decode_region_with<R: io::Read, W: io::Write>(
+  reader: R,
+  writer: W,
+  rect: Rect,
+  options: &DecodeOptions,
+) -> Result<ImageMeta, Error> {
+  options.check_supported(
+    SupportedOptions::BACKGROUND
+      | SupportedOptions::FORMAT
+      | SupportedOptions::PREMULTIPLY
+  )?;
+
+  let mut decoder = Decoder::with_options_unchecked(reader, options)?;
+  let meta = *decoder.meta();
+
+  if !rect.is_within(&meta) {
+    return Err(Error::InvalidRect);
+  }
+
+  let output = *decoder.output();
+  let pixel_len = output.format.bytes_per_pixel();
+  let mut row = vec![0; decoded_len(rect.width as u64, pixel_len).ok_or(Error::InvalidDimensions)?];
+
+  // Pixels between the end of one row of the rectangle and the start of the
+  // next.
+  let gap = meta.width as u64 - rect.width as u64;
+  let mut writer = WriteBuffer::new(writer);
+
+  if rect.width > 0 {
+    decoder.skip_pixels(rect.y as u64 * meta.width as u64 + rect.x as u64)?;
+
+    for y in 0..rect.height {
+      if y > 0 {
+        decoder.skip_pixels(gap)?;
       }
-      Colorspace::Srgb => {
-        writer.write_all(&[pixel.r, pixel.g, pixel.b, pixel.a])?;
+
+      for pixel_buf in row.chunks_exact_mut(pixel_len) {
+        if let Some(pixel) = decoder.next_pixel()? {
+          output.write_pixel(pixel, pixel_buf);
+        }
       }
+
+      writer.write_all(&row)?;
     }
   }
 
@@ -49,11 +348,390 @@ pub fn decode_image<R: io::Read, W: io::Write>(
   Ok(meta)
 }
 
-// Attempts to decode the image's header, returning the image's `ImageMeta`
-// data upon success.
-fn decode_header<R: io::Read>(mut reader: R) -> Result<ImageMeta, Error> {
+/// Decodes a QOI encoded image into separate planes, one per color channel,
+/// as used by CHW tensors. Each decoded channel is written to its own
+/// `writer` in `planes`, a row at a time, and upon success, the image's
+/// `ImageMeta` data is returned.
+/// 
+/// The number of planes selects the channels that are written, 3 for red,
+/// green, and blue, or 4 to also write alpha, regardless of the number of
+/// channels the image has. Images with 3 channels are fully opaque.
+/// `Error::InvalidChannels` is returned for any other number of planes.
+/// 
+/// Alpha is never dropped silently, so decoding an image with alpha into 3
+/// planes fails with `Error::MissingBackground`. To flatten it onto a
+/// background color instead, use `decode_planar_with` and
+/// `DecodeOptions::background`.
+/// 
+/// ```rust
+/// use qoi::decode_planar;
+/// 
+/// let image = include_bytes!("../tests/testcard_rgb_256x256.qoi");
+/// let mut planes = vec![Vec::new(); 3];
+/// 
+/// decode_planar(image.as_slice(), &mut planes).expect("Failed to decode image");
+/// 
+/// // All red values, then all green values, then all blue values.
+/// let chw = planes.concat();
+/// assert_eq!(chw.len(), 3 * 256 * 256);
+/// ```
+#[cfg(feature = "std")]
+pub fn decode_planar<R: io::Read, W: io::Write>(
+  reader: R,
+  planes: &mut [W],
+) -> Result<ImageMeta, Error> {
+  decode_planar_with(reader, planes, &DecodeOptions::default())
+}
+
+/// Decodes a QOI encoded image into separate planes, one per color channel,
+/// like `decode_planar`, customized by the given `options`. The image's
+/// header is checked against `options.limits` before any pixels are decoded.
+/// 
+/// Only the `background` and `strict` options are supported,
+/// `Error::UnsupportedOption` is returned for any other option. When an
+/// image with alpha is decoded into 3 planes, its pixels are composited onto
+/// the `background` before they're split into planes.
+#[cfg(feature = "std")]
+pub fn decode_planar_with<R: io::Read, W: io::Write>(
+  reader: R,
+  planes: &mut [W],
+  options: &DecodeOptions,
+) -> Result<ImageMeta, Error> {
+  options.check_supported(SupportedOptions::BACKGROUND | SupportedOptions::STRICT)?;
+
+  let channels = validate_planes(planes.len())?;
+  let mut decoder = Decoder::with_options_unchecked(reader, options)?;
+  let meta = *decoder.meta();
+  let output = planar_output(&meta, channels, options)?;
+  let width = decoded_len(meta.width as u64, 1).ok_or(Error::InvalidDimensions)?;
+  let mut planes: Vec<_> = planes.iter_mut().map(WriteBuffer::new).collect();
+
+  if meta.num_pixels() > 0 {
+    let mut rows = vec![0; decoded_len(meta.width as u64, channels).ok_or(Error::InvalidDimensions)?];
+
+    for _ in 0..meta.height {
+      for x in 0..width {
+        if let Some(pixel) = decoder.next_pixel()? {
+          let mut values = [0; 4];
+          output.write_pixel(pixel, &mut values);
+
+          for (row, value) in rows.chunks_exact_mut(width).zip(values) {
+            row[x] = value;
+          }
+        }
+      }
+
+      for (plane, row) in planes.iter_mut().zip(rows.chunks_exact(width)) {
+        plane.write_all(row)?;
+      }
+    }
+  }
+
+  for plane in planes.iter_mut() {
+    plane.flush()?;
+  }
+
+  decoder.finish()?;
+
+  Ok(meta)
+}
+
+/// Decodes a QOI encoded image held in memory into separate planes, one per
+/// color channel, like `decode_planar`. Each plane in `planes` must be at
+/// least `width * height` bytes long, otherwise `Error::BufferTooSmall` is
+/// returned before anything is decoded.
+/// 
+/// To decode into a single CHW buffer, split it into planes with
+/// `chunks_exact_mut`.
+pub fn decode_planar_to_slices(source: &[u8], planes: &mut [&mut [u8]]) -> Result<ImageMeta, Error> {
+  decode_planar_to_slices_with(source, planes, &DecodeOptions::default())
+}
+
+/// Decodes a QOI encoded image held in memory into separate planes, one per
+/// color channel, like `decode_planar_to_slices`, customized by the given
+/// `options`. The image's header is checked against `options.limits` before
+/// any pixels are decoded.
+/// 
+/// Like `decode_planar_with`, only the `background` and `strict` options are
+/// supported, `Error::UnsupportedOption` is returned for any other option.
+pub fn decode_planar_to_slices_with(
+  source: &[u8],
+  planes: &mut [&mut [u8]],
+  options: &DecodeOptions,
+) -> Result<ImageMeta, Error> {
+  options.check_supported(SupportedOptions::BACKGROUND | SupportedOptions::STRICT)?;
+
+  let channels = validate_planes(planes.len())?;
+  let meta = decode_header_from_slice(source)?;
+  let output = planar_output(&meta, channels, options)?;
+
+  options.limits.check(&meta, channels)?;
+
+  let plane_len = decoded_len(meta.num_pixels(), 1).ok_or(Error::InvalidDimensions)?;
+
+  if planes.iter().any(|plane| plane.len() < plane_len) {
+    return Err(Error::BufferTooSmall(plane_len));
+  }
+
+  let mut decoder = SliceDecoder::new(source, &meta);
+
+  for index in 0..plane_len {
+    let pixel = decoder.next_pixel(index as u64)?;
+    let mut values = [0; 4];
+    output.write_pixel(pixel, &mut values);
+
+    for (plane, value) in planes.iter_mut().zip(values) {
+      plane[index] = value;
+    }
+  }
+
+  decoder.finish(options)?;
+
+  Ok(meta)
+}
+
+/// Decodes a QOI encoded image held in memory into the provided `dest`
+/// slice, returning the image's `ImageMeta` data upon success.
+/// 
+/// Pixels are written as RGB or RGBA to match the image's header, so `dest`
+/// must be at least `width * height * channels` bytes long, otherwise
+/// `Error::BufferTooSmall` is returned before anything is decoded. Any bytes
+/// in `dest` past the decoded pixels are left untouched.
+/// 
+/// This is the fastest way to decode an image, since there's no IO involved.
+/// It doesn't allocate and is available without the `std` and `alloc`
+/// features.
+pub fn decode_to_slice(source: &[u8], dest: &mut [u8]) -> Result<ImageMeta, Error> {
+  decode_to_slice_with(source, dest, &DecodeOptions::default()).map(|report| report.meta)
+}
+
+/// Decodes a QOI encoded image held in memory into the provided `dest`
+/// slice, customized with the given `DecodeOptions`, returning a
+/// `DecodeReport` upon success.
+/// 
+/// Rows of pixels are written `DecodeOptions::stride` bytes apart, so the
+/// decoded image can be written straight into a buffer with padded rows, such
+/// as a GPU upload buffer. The padding between rows is left untouched. `dest`
+/// must be at least `stride * (height - 1)` bytes long plus the length of a
+/// row, since the last row doesn't need padding, otherwise
+/// `Error::BufferTooSmall` is returned before anything is decoded.
+/// 
+/// When `DecodeOptions::flip_vertically` is `true`, the image's rows are
+/// written bottom-up.
+/// 
+/// When decoding strictly, `source` must end with the image's end marker.
+/// When recovering from an error with `Recovery::Truncate`, the pixels that
+/// weren't decoded are left untouched.
+/// 
+/// Like `decode_to_slice`, this function doesn't allocate and is available
+/// without the `std` and `alloc` features.
+pub fn decode_to_slice_with(
+  source: &[u8],
+  dest: &mut [u8],
+  options: &DecodeOptions,
+) -> Result<DecodeReport, Error> {
+  check_slice_options(options)?;
+
+  let meta = decode_header_from_slice(source)?;
+  let output = Output::new(&meta, options)?;
+  let pixel_len = output.format.bytes_per_pixel();
+
+  options.limits.check(&meta, pixel_len)?;
+
+  // Decoding as RGB or RGBA without converting pixels is the common case, so
+  // it gets its own copies of the hot loop.
+  match output.format {
+    PixelFormat::Rgba if output.is_plain() => {
+      decode_slice_rows(source, &meta, dest, pixel_len, options, |pixel, buf| {
+        buf.copy_from_slice(&[pixel.r, pixel.g, pixel.b, pixel.a]);
+      })
+    }
+    PixelFormat::Rgb if output.is_plain() => {
+      decode_slice_rows(source, &meta, dest, pixel_len, options, |pixel, buf| {
+        buf.copy_from_slice(&[pixel.r, pixel.g, pixel.b]);
+      })
+    }
+    _ => {
+      decode_slice_rows(source, &meta, dest, pixel_len, options, |pixel, buf| {
+        output.write_pixel(pixel, buf);
+      })
+    }
+  }
+}
+
+/// Decodes a QOI encoded image held in memory into the provided `dest`
+/// slice as normalized `f32` values in [0, 1], customized with the given
+/// `DecodeOptions`, returning a `DecodeReport` upon success.
+/// 
+/// Pixels are converted and laid out like with `decode_to_slice_with`, with
+/// one `f32` value per channel, so `dest` and `DecodeOptions::stride` are
+/// measured in values rather than bytes. For example, pixels are written as
+/// RGB or RGBA to match the image's header by default, so `dest` must be at
+/// least `width * height * channels` values long, otherwise
+/// `Error::BufferTooSmall` is returned before anything is decoded.
+/// 
+/// When `DecodeOptions::linearize` is `true` and the image's colorspace is
+/// `Colorspace::Srgb`, color channels are converted from sRGB to linear
+/// light with the sRGB transfer function. Otherwise, and always for alpha,
+/// values are only normalized. The conversion is exact, every 8 bit value is
+/// mapped to the nearest `f32`.
+/// 
+/// When the decoded values are in linear light, either because they're
+/// linearized or because the image's colorspace is `Colorspace::Linear`,
+/// `DecodeOptions::background` and `DecodeOptions::premultiply` are applied
+/// to them after they're converted, so nothing is rounded to 8 bits, and gray
+/// values are the luma of the linear values. Otherwise, pixels are converted
+/// like with `decode_to_slice_with` before they're normalized.
+pub fn decode_to_f32_slice(
+  source: &[u8],
+  dest: &mut [f32],
+  options: &DecodeOptions,
+) -> Result<DecodeReport, Error> {
+  let meta = decode_header_from_slice(source)?;
+  let output = Output::new(&meta, options)?;
+  let pixel_len = output.format.bytes_per_pixel();
+
+  options.limits.check(&meta, pixel_len * mem::size_of::<f32>())?;
+
+  match meta.colorspace {
+    Colorspace::Srgb if options.linearize => {
+      decode_slice_rows(source, &meta, dest, pixel_len, options, |pixel, values| {
+        output.write_linear_pixel(pixel, srgb_to_linear, values);
+      })
+    }
+    Colorspace::Linear => {
+      decode_slice_rows(source, &meta, dest, pixel_len, options, |pixel, values| {
+        output.write_linear_pixel(pixel, unorm_to_f32, values);
+      })
+    }
+    Colorspace::Srgb => {
+      let mut buf = [0; 4];
+
+      decode_slice_rows(source, &meta, dest, pixel_len, options, |pixel, values| {
+        output.write_pixel(pixel, &mut buf);
+
+        for (value, &byte) in values.iter_mut().zip(&buf) {
+          *value = unorm_to_f32(byte);
+        }
+      })
+    }
+  }
+}
+
+/// Decodes a QOI encoded image held in memory into a newly allocated `Vec`
+/// of normalized `f32` values, see `decode_to_f32_slice`. Like
+/// `decode_to_vec_with`, the buffer is only allocated once the image's header
+/// has been checked against the length of `source`, unless recovering, and
+/// against `DecodeOptions::limits`.
+/// 
+/// Requires the `alloc` feature, which is enabled by default through `std`.
+#[cfg(feature = "alloc")]
+pub fn decode_to_f32_vec(
+  source: &[u8],
+  options: &DecodeOptions,
+) -> Result<(DecodeReport, Vec<f32>), Error> {
+  let meta = decode_header_from_slice(source)?;
+  check_source_len(source, &meta, options)?;
+
+  let pixel_len = Output::new(&meta, options)?.format.bytes_per_pixel();
+
+  options.limits.check(&meta, pixel_len * mem::size_of::<f32>())?;
+
+  let (_, dest_len) = slice_layout(&meta, pixel_len, options)?;
+  let mut dest = vec![0.0; dest_len];
+  let report = decode_to_f32_slice(source, &mut dest, options)?;
+
+  Ok((report, dest))
+}
+
+/// Decodes a QOI encoded image held in memory into a newly allocated `Vec`,
+/// returning the image's `ImageMeta` data along with the decoded pixels upon
+/// success. See `decode_to_slice`.
+/// 
+/// The buffer is only allocated once the image's header has been checked
+/// against the length of `source`, so a small image can't claim an absurdly
+/// large number of pixels.
+/// 
+/// Requires the `alloc` feature, which is enabled by default through `std`.
+#[cfg(feature = "alloc")]
+pub fn decode_to_vec(source: &[u8]) -> Result<(ImageMeta, Vec<u8>), Error> {
+  decode_to_vec_with(source, &DecodeOptions::default()).map(|(report, dest)| (report.meta, dest))
+}
+
+/// Decodes a QOI encoded image held in memory into a newly allocated `Vec`,
+/// customized with the given `DecodeOptions`, returning a `DecodeReport`
+/// along with the decoded pixels upon success. See `decode_to_slice_with`.
+/// 
+/// Like `decode_to_vec`, the buffer is only allocated once the image's header
+/// has been checked against the length of `source`, and against
+/// `DecodeOptions::limits`. When recovering with `DecodeOptions::recovery`,
+/// a truncated image is expected to claim more pixels than it holds, so it's
+/// only checked against the limits, which should be set when decoding
+/// untrusted images. With `Recovery::Fill`, the buffer always holds every
+/// pixel of the image.
+/// 
+/// Requires the `alloc` feature, which is enabled by default through `std`.
+#[cfg(feature = "alloc")]
+pub fn decode_to_vec_with(
+  source: &[u8],
+  options: &DecodeOptions,
+) -> Result<(DecodeReport, Vec<u8>), Error> {
+  check_slice_options(options)?;
+
+  let meta = decode_header_from_slice(source)?;
+  check_source_len(source, &meta, options)?;
+
+  let pixel_len = Output::new(&meta, options)?.format.bytes_per_pixel();
+
+  options.limits.check(&meta, pixel_len)?;
+
+  let (_, dest_len) = slice_layout(&meta, pixel_len, options)?;
+  let mut dest = vec![0; dest_len];
+  let report = decode_to_slice_with(source, &mut dest, options)?;
+
+  Ok((report, dest))
+}
+
+/// Reads and decodes only the header of a QOI encoded image, returning the
+/// image's `ImageMeta` data upon success. At most `QOI_HEADER_LEN` bytes are
+/// read from the `reader`, a truncated header is reported as
+/// `Error::UnexpectedEof`.
+/// 
+/// This is useful to inspect an image's dimensions without decoding it, for
+/// example to allocate a buffer of `ImageMeta::decoded_len` bytes up front.
+#[cfg(feature = "std")]
+pub fn read_header<R: io::Read>(mut reader: R) -> Result<ImageMeta, Error> {
   let mut header_buf = [0; QOI_HEADER_LEN];
-  reader.read_exact(&mut header_buf)?;
+  let mut len = 0;
+
+  // Like `read_exact`, but reports a truncated header the same way as a
+  // truncated image body.
+  while len < QOI_HEADER_LEN {
+    match reader.read(&mut header_buf[len..]) {
+      Ok(0) => break,
+      Ok(read) => len += read,
+      Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+      Err(e) => return Err(e.into()),
+    }
+  }
+
+  decode_header_from_slice(&header_buf[..len])
+}
+
+/// Checks if the given bytes start with a valid QOI image header.
+/// 
+/// Only the header is checked, the rest of the image may still be malformed
+/// or incomplete.
+pub fn is_qoi(bytes: &[u8]) -> bool {
+  decode_header_from_slice(bytes).is_ok()
+}
+
+// Attempts to decode the image's header from the start of the given bytes,
+// returning the image's `ImageMeta` data upon success.
+pub(crate) fn decode_header_from_slice(bytes: &[u8]) -> Result<ImageMeta, Error> {
+  let header_buf = bytes
+    .get(..QOI_HEADER_LEN)
+    .ok_or_else(|| Error::UnexpectedEof(Some(ErrorLocation::header())))?;
 
   if &header_buf[..4] != QOI_BYTES_MAGIC {
     return Err(Error::InvalidHeader);
@@ -62,23 +740,262 @@ fn decode_header<R: io::Read>(mut reader: R) -> Result<ImageMeta, Error> {
   Ok(ImageMeta {
     width: u32::from_be_bytes(header_buf[4..8].try_into()?),
     height: u32::from_be_bytes(header_buf[8..12].try_into()?),
-    channels: header_buf[12],
+    channels: validate_channels(header_buf[12])?,
     colorspace: Colorspace::try_from(header_buf[13])?,
   })
 }
 
+// Checks that the image held in `source`, described by `meta`, could
+// possibly encode all of its pixels. Every encoded pixel takes up at least
+// part of a byte, so an image that claims more pixels than its bytes could
+// possibly encode is incomplete. Checking this first avoids allocating a
+// buffer for a bogus image. When recovering, incomplete images are expected,
+// so only `DecodeOptions::limits` bound the buffer.
+#[cfg(feature = "alloc")]
+fn check_source_len(source: &[u8], meta: &ImageMeta, options: &DecodeOptions) -> Result<(), Error> {
+  let max_pixels = (source.len() - QOI_HEADER_LEN) as u64 * QOI_MAX_RUN as u64;
+
+  if options.recovery.is_none() && meta.num_pixels() > max_pixels {
+    return Err(Error::UnexpectedEof(None));
+  }
+
+  Ok(())
+}
+
+// Fails with `Error::UnsupportedOption` if an option that decoding into a
+// slice of bytes doesn't support is set.
+fn check_slice_options(options: &DecodeOptions) -> Result<(), Error> {
+  options.check_supported(
+    SupportedOptions::BACKGROUND
+      | SupportedOptions::FLIP_VERTICALLY
+      | SupportedOptions::FORMAT
+      | SupportedOptions::PREMULTIPLY
+      | SupportedOptions::PROGRESS
+      | SupportedOptions::RECOVERY
+      | SupportedOptions::STRIDE
+      | SupportedOptions::STRICT
+  )
+}
+
+// Computes the stride between the rows of the image described by `meta`
+// when it's decoded into a slice with `pixel_len` values per pixel, as
+// configured by `options`, along with the length of the slice needed to hold
+// all of the rows. The last row isn't padded.
+fn slice_layout(
+  meta: &ImageMeta,
+  pixel_len: usize,
+  options: &DecodeOptions,
+) -> Result<(usize, usize), Error> {
+  let row_len = (meta.width as usize).checked_mul(pixel_len).ok_or(Error::InvalidDimensions)?;
+  let stride = options.stride.unwrap_or(row_len);
+
+  if stride < row_len {
+    return Err(Error::InvalidStride);
+  }
+
+  let len = match meta.height {
+    0 => Some(0),
+    height => stride.checked_mul(height as usize - 1).and_then(|len| len.checked_add(row_len)),
+  };
+
+  Ok((stride, len.ok_or(Error::InvalidDimensions)?))
+}
+
+// Decodes the image held in `source`, described by `meta`, into the rows of
+// `dest` as laid out by `options`, passing each pixel to `write` along with
+// the `pixel_len` values of `dest` to write it to.
+//
+// This is the hot loop of every slice based decode function, keep it tight.
+// Rows are walked with `chunks_exact_mut`, so writing a pixel isn't bounds
+// checked, and flipping, stride, and progress are only dealt with once per
+// row. `write` is monomorphised into the loop, so callers should pick a
+// closure that does no more than their options require.
+#[inline]
+fn decode_slice_rows<T, F: FnMut(Pixel, &mut [T])>(
+  source: &[u8],
+  meta: &ImageMeta,
+  dest: &mut [T],
+  pixel_len: usize,
+  options: &DecodeOptions,
+  mut write: F,
+) -> Result<DecodeReport, Error> {
+  let (stride, dest_len) = slice_layout(meta, pixel_len, options)?;
+
+  if dest.len() < dest_len {
+    return Err(Error::BufferTooSmall(dest_len));
+  }
+
+  let width = meta.width as usize;
+  let height = meta.height as usize;
+  let row_len = width * pixel_len;
+  let mut decoder = SliceDecoder::new(source, meta);
+  let mut progress = ProgressTracker::new(options.progress, meta);
+  let mut index = 0;
+
+  if row_len > 0 {
+    for y in 0..height {
+      let row = if options.flip_vertically { height - 1 - y } else { y };
+      let row = &mut dest[row * stride..row * stride + row_len];
+
+      for pixel_buf in row.chunks_exact_mut(pixel_len) {
+        match decoder.next_pixel(index) {
+          Ok(pixel) => write(pixel, pixel_buf),
+          Err(err) => {
+            return recover_slice_pixels(meta, index, err, options, &mut progress, |index, pixel| {
+              let (y, x) = (index / width, index % width);
+              let row = if options.flip_vertically { height - 1 - y } else { y };
+              let start = row * stride + x * pixel_len;
+
+              write(pixel, &mut dest[start..start + pixel_len]);
+            });
+          }
+        }
+
+        index += 1;
+      }
+
+      progress.update(index)?;
+    }
+  }
+
+  decoder.finish(options)
+}
+
+// Decodes the pixels of an image held in memory one by one, keeping track of
+// the encoding state and the position within the image's bytes.
+struct SliceDecoder<'a> {
+  bytes: &'a [u8],
+  meta: &'a ImageMeta,
+  pos: usize,
+  state: State,
+}
+
+impl<'a> SliceDecoder<'a> {
+  fn new(source: &'a [u8], meta: &'a ImageMeta) -> Self {
+    Self { bytes: &source[QOI_HEADER_LEN..], meta, pos: 0, state: State::new() }
+  }
+
+  // Decodes the next pixel, which is the pixel at `index`, attaching its
+  // location to any error.
+  #[inline]
+  fn next_pixel(&mut self, index: u64) -> Result<Pixel, Error> {
+    decode_pixel(&mut self.state, self.bytes, &mut self.pos).map_err(|err| self.locate(err, index))
+  }
+
+  #[cold]
+  fn locate(&self, err: Error, index: u64) -> Error {
+    let offset = (QOI_HEADER_LEN + self.pos) as u64;
+    err.at(ErrorLocation::new(self.meta, offset, index, self.state.last_op))
+  }
+
+  // Checks the end marker that follows the image's last pixel, once all of
+  // them are decoded, and validates it as configured by `options`.
+  fn finish(&self, options: &DecodeOptions) -> Result<DecodeReport, Error> {
+    let num_pixels = self.meta.num_pixels();
+    let rest = &self.bytes[self.pos..];
+    let mut end_marker = EndMarker::check(rest);
+
+    if options.strict {
+      end_marker = end_marker.require_valid().map_err(|err| self.locate(err, num_pixels))?;
+
+    }
+
+    let trailing_bytes = rest.len().saturating_sub(QOI_BYTES_END.len());
+
+    if options.strict && trailing_bytes > 0 {
+      let offset = (QOI_HEADER_LEN + self.pos + QOI_BYTES_END.len()) as u64;
+      let location = ErrorLocation::new(self.meta, offset, num_pixels, self.state.last_op);
+      return Err(Error::TrailingData(location));
+    }
+
+    Ok(DecodeReport {
+      meta: *self.meta,
+      end_marker,
+      pixels_decoded: num_pixels,
+      error: None,
+      trailing_bytes: Some(trailing_bytes),
+      has_trailing_data: trailing_bytes > 0,
+    })
+  }
+}
+
+// Recovers from the given error, which occurred decoding the pixel at
+// `index`, as configured by `options`, passing the fill pixel (if any) to `f`
+// in place of each remaining pixel. Without a recovery option, the error is
+// returned.
+#[cold]
+fn recover_slice_pixels<F: FnMut(usize, Pixel)>(
+  meta: &ImageMeta,
+  index: u64,
+  err: Error,
+  options: &DecodeOptions,
+  progress: &mut ProgressTracker,
+  mut f: F,
+) -> Result<DecodeReport, Error> {
+  match options.recovery {
+    Some(recovery) if err.is_recoverable() => {
+      if let Recovery::Fill(pixel) = recovery {
+        for index in index..meta.num_pixels() {
+          f(index as usize, pixel);
+          progress.update(index + 1)?;
+        }
+      }
+
+      Ok(DecodeReport {
+        meta: *meta,
+        end_marker: EndMarker::Truncated(0),
+        pixels_decoded: index,
+        error: Some(err),
+        trailing_bytes: None,
+        has_trailing_data: false,
+      })
+    }
+    _ => Err(err),
+  }
+}
+
+// Determines the output of the pixels of the image described by `meta` when
+// they're split into the given number of planes, as RGB for 3 planes or RGBA
+// for 4. Compositing onto `options.background` is the only conversion.
+fn planar_output(
+  meta: &ImageMeta,
+  channels: usize,
+  options: &DecodeOptions,
+) -> Result<Output, Error> {
+  let format = if channels == 3 { PixelFormat::Rgb } else { PixelFormat::Rgba };
+  let options = DecodeOptions {
+    background: options.background,
+    format: Some(format),
+    ..Default::default()
+  };
+
+  Output::new(meta, &options)
+}
+
+// Checks that the given number of planes is supported, returning the number
+// of channels they hold.
+fn validate_planes(len: usize) -> Result<usize, Error> {
+  match len {
+    3 | 4 => Ok(len),
+    _ => Err(Error::InvalidChannels(u8::try_from(len).unwrap_or(u8::MAX))),
+  }
+}
+
 // Attempts to decode a single "next" pixel from the provided encoding `state`
-// and encoded `bytes`.
-fn decode_pixel<I: Iterator<Item = Result<u8, io::Error>>>(
-  state: &mut State,
-  bytes: &mut I,
-) -> Result<Pixel, Error> {
+// and encoded `bytes`, starting at `pos`. Upon success, `pos` is advanced
+// past the decoded `Op` and the `state` is updated with the decoded pixel.
+#[inline]
+pub(crate) fn decode_pixel(state: &mut State, bytes: &[u8], pos: &mut usize) -> Result<Pixel, Error> {
   if state.run_count > 0 {
     state.run_count -= 1;
     return Ok(state.prev_pixel);
   }
 
-  let pixel = match Op::try_from_bytes(bytes)? {
+  let (op, len) = Op::try_from_slice(bytes.get(*pos..).unwrap_or_default())?;
+  *pos += len;
+  state.last_op = Some(op.kind());
+
+  let pixel = match op {
     Op::Color(diff_r, diff_g, diff_b) => {
       Pixel::from_diff(PixelDiff::Color(diff_r, diff_g, diff_b), &state.prev_pixel)
     }
@@ -100,13 +1017,16 @@ fn decode_pixel<I: Iterator<Item = Result<u8, io::Error>>>(
     }
   };
 
+  if pixel != state.prev_pixel {
+    state.cache_insert(pixel);
+    state.prev_pixel = pixel;
+  }
+
   Ok(pixel)
 }
 
 #[cfg(test)]
 mod tests {
-  use std::io::Read;
-
   use super::*;
 
   #[test]
@@ -119,7 +1039,7 @@ mod tests {
     header.extend_from_slice(&[4, 0]);
 
     assert_eq!(
-      decode_header(header.as_slice()),
+      read_header(header.as_slice()),
       Ok(ImageMeta { width: 0, height: 0, channels: 4, colorspace: Colorspace::Srgb })
     );
   }
@@ -128,12 +1048,24 @@ mod tests {
   fn test_decoding_invalid_image_header() {
     let mut header = Vec::new();
 
-    header.extend_from_slice(&[b'q', b'q', b'q', b'q']);
+    header.extend_from_slice(b"qqqq");
     header.extend_from_slice(&0usize.to_be_bytes());
     header.extend_from_slice(&0usize.to_be_bytes());
     header.extend_from_slice(&[5, 2]);
 
-    assert!(decode_header(header.as_slice()).is_err());
+    assert!(read_header(header.as_slice()).is_err());
+  }
+
+  #[test]
+  fn test_decoding_image_header_with_invalid_channels() {
+    let mut header = Vec::new();
+
+    header.extend_from_slice(QOI_BYTES_MAGIC);
+    header.extend_from_slice(&1u32.to_be_bytes());
+    header.extend_from_slice(&1u32.to_be_bytes());
+    header.extend_from_slice(&[2, 0]);
+
+    assert_eq!(read_header(header.as_slice()), Err(Error::InvalidChannels(2)));
   }
 
   #[test]
@@ -146,7 +1078,7 @@ mod tests {
       .expect("Failed to write op");
 
     assert_eq!(
-      decode_pixel(&mut state, &mut source.as_slice().bytes()),
+      decode_pixel(&mut state, &source, &mut 0),
       Ok(Pixel { r: 101, g: 102, b: 103, a: 255 })
     );
   }
@@ -161,7 +1093,7 @@ mod tests {
       .expect("Failed to write op");
 
     assert_eq!(
-      decode_pixel(&mut state, &mut source.as_slice().bytes()),
+      decode_pixel(&mut state, &source, &mut 0),
       Ok(Pixel { r: 101, g: 102, b: 103, a: 104 })
     );
   }
@@ -179,7 +1111,7 @@ mod tests {
     state.prev_pixel = pixel;
 
     assert_eq!(
-      decode_pixel(&mut state, &mut source.as_slice().bytes()),
+      decode_pixel(&mut state, &source, &mut 0),
       Ok(pixel)
     );
 
@@ -199,7 +1131,7 @@ mod tests {
     state.cache_insert(pixel);
 
     assert_eq!(
-      decode_pixel(&mut state, &mut source.as_slice().bytes()),
+      decode_pixel(&mut state, &source, &mut 0),
       Ok(pixel)
     );
   }
@@ -225,7 +1157,7 @@ mod tests {
     };
 
     assert_eq!(
-      decode_pixel(&mut state, &mut source.as_slice().bytes()),
+      decode_pixel(&mut state, &source, &mut 0),
       Ok(pixel_b)
     );
   }
@@ -251,7 +1183,7 @@ mod tests {
     };
 
     assert_eq!(
-      decode_pixel(&mut state, &mut source.as_slice().bytes()),
+      decode_pixel(&mut state, &source, &mut 0),
       Ok(pixel_b)
     );
   }
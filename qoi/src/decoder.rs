@@ -0,0 +1,350 @@
+use std::io;
+
+use crate::buffer::ReadBuffer;
+use crate::decode::{decode_pixel, read_header};
+use crate::error::{Error, ErrorLocation};
+use crate::format::{Output, PixelFormat};
+use crate::meta::{decoded_len, ImageMeta, QOI_BYTES_END, QOI_HEADER_LEN};
+use crate::op::Op;
+use crate::options::{DecodeOptions, Recovery, SupportedOptions};
+use crate::pixel::Pixel;
+use crate::reader::DecoderReader;
+use crate::report::{DecodeReport, EndMarker};
+use crate::state::State;
+use crate::stats::Observer;
+
+/// A pull-based QOI decoder that decodes an image one row at a time, which
+/// keeps memory usage bound to a single row regardless of the image's size.
+/// 
+/// The image's header is decoded up front when the decoder is created, so
+/// its `ImageMeta` data is available before any pixels are decoded.
+/// 
+/// ```rust
+/// use qoi::Decoder;
+/// 
+/// let image = include_bytes!("../tests/testcard_rgba_256x256.qoi");
+/// let mut decoder = Decoder::new(image.as_slice()).expect("Failed to decode header");
+/// let mut row = vec![0; decoder.row_len()];
+/// 
+/// while let Some(y) = decoder.next_row(&mut row).expect("Failed to decode row") {
+///   // `row` contains the pixels of row `y`.
+/// }
+/// 
+/// decoder.finish().expect("Failed to decode image");
+/// ```
+pub struct Decoder<R> {
+  input: ReadBuffer<R>,
+  meta: ImageMeta,
+  // The byte offset of the next `Op` from the start of the encoded image.
+  offset: u64,
+  output: Output,
+  // The number of pixels decoded so far.
+  pixel_index: u64,
+  // The number of pixels decoded before the error decoding recovered from,
+  // and the error.
+  recovered: Option<(u64, Error)>,
+  recovery: Option<Recovery>,
+  row_len: usize,
+  state: State,
+  strict: bool,
+}
+
+impl<R: io::Read> Decoder<R> {
+  /// Creates a new decoder that decodes an image as described by its header,
+  /// decoding the header from the given `reader`.
+  pub fn new(reader: R) -> Result<Self, Error> {
+    Self::with_options(reader, &DecodeOptions::default())
+  }
+
+  /// Creates a new decoder customized by the given `options`, see
+  /// `decode_image_with`. The image's header is decoded from the given
+  /// `reader` and checked against `options.limits`.
+  /// 
+  /// The `flip_vertically`, `linearize`, `progress`, and `stride` options
+  /// aren't supported.
+  pub fn with_options(reader: R, options: &DecodeOptions) -> Result<Self, Error> {
+    options.check_supported(
+      SupportedOptions::BACKGROUND
+        | SupportedOptions::FORMAT
+        | SupportedOptions::PREMULTIPLY
+        | SupportedOptions::RECOVERY
+        | SupportedOptions::STRICT
+    )?;
+
+    Self::with_options_unchecked(reader, options)
+  }
+
+  // Creates a new decoder like `with_options`, for functions that support
+  // more options than the decoder itself, which they've checked already.
+  pub(crate) fn with_options_unchecked(
+    mut reader: R,
+    options: &DecodeOptions,
+  ) -> Result<Self, Error> {
+    let meta = read_header(&mut reader)?;
+    let output = Output::new(&meta, options)?;
+
+    options.limits.check(&meta, output.format.bytes_per_pixel())?;
+
+    let row_len = decoded_len(meta.width as u64, output.format.bytes_per_pixel())
+      .ok_or(Error::InvalidDimensions)?;
+
+    Ok(Self {
+      input: ReadBuffer::new(reader),
+      meta,
+      offset: QOI_HEADER_LEN as u64,
+      output,
+      pixel_index: 0,
+      recovered: None,
+      recovery: options.recovery,
+      row_len,
+      state: State::new(),
+      strict: options.strict,
+    })
+  }
+
+  /// Returns the image's `ImageMeta` data, as found in its header.
+  pub fn meta(&self) -> &ImageMeta {
+    &self.meta
+  }
+
+  /// Returns the pixel format rows are decoded in.
+  pub fn format(&self) -> PixelFormat {
+    self.output.format
+  }
+
+  /// Returns the number of bytes a single decoded row occupies.
+  pub fn row_len(&self) -> usize {
+    self.row_len
+  }
+
+  /// Decodes the next row of the image into the start of `row`, which must
+  /// be at least `row_len` bytes long. Returns the row's y index, or `None`
+  /// once all rows have been decoded.
+  /// 
+  /// When recovering with `Recovery::Truncate`, the row that failed to
+  /// decode is still returned, the bytes of its missing pixels are left
+  /// untouched.
+  pub fn next_row(&mut self, row: &mut [u8]) -> Result<Option<u32>, Error> {
+    if self.is_done() {
+      return Ok(None);
+    }
+
+    let row = row.get_mut(..self.row_len).ok_or(Error::BufferTooSmall(self.row_len))?;
+    let y = (self.pixel_index / self.meta.width as u64) as u32;
+
+    for pixel_buf in row.chunks_exact_mut(self.output.format.bytes_per_pixel()) {
+      match self.next_pixel()? {
+        Some(pixel) => self.output.write_pixel(pixel, pixel_buf),
+        None => break,
+      }
+    }
+
+    Ok(Some(y))
+  }
+
+  /// Converts the decoder into an iterator over the image's remaining
+  /// pixels, see `Pixels`.
+  pub fn into_pixels(self) -> Pixels<R> {
+    Pixels { decoder: Some(self) }
+  }
+
+  /// Converts the decoder into an `std::io::Read` implementation over the
+  /// image's remaining decoded pixel data, see `DecoderReader`.
+  pub fn into_reader(self) -> DecoderReader<R> {
+    DecoderReader::from(self)
+  }
+
+  /// Completes decoding, returning the image's `DecodeReport` upon success.
+  /// Any rows that haven't been decoded yet are decoded and discarded first,
+  /// then the end marker is read, and validated when decoding strictly.
+  /// 
+  /// After recovering from an error, see `DecodeOptions::recovery`, the end
+  /// marker isn't read and the error is reported instead.
+  pub fn finish(mut self) -> Result<DecodeReport, Error> {
+    while self.next_pixel()?.is_some() {}
+
+    if let Some((pixels_decoded, error)) = self.recovered {
+      return Ok(DecodeReport {
+        meta: self.meta,
+        end_marker: EndMarker::Truncated(0),
+        pixels_decoded,
+        error: Some(error),
+        trailing_bytes: None,
+        has_trailing_data: false,
+      });
+    }
+
+    let bytes = self.input.fill(QOI_BYTES_END.len())?;
+    let mut end_marker = EndMarker::check(bytes);
+    let len = bytes.len().min(QOI_BYTES_END.len());
+
+    if self.strict {
+      end_marker = end_marker.require_valid().map_err(|e| e.at(self.location()))?;
+    }
+
+    self.input.consume(len);
+    self.offset += len as u64;
+
+    // Strict decoding reads on to make sure the image source has ended,
+    // otherwise only data that's already buffered is looked at.
+    let has_trailing_data = !self.input.fill(self.strict as usize)?.is_empty();
+
+    if self.strict && has_trailing_data {
+      return Err(Error::TrailingData(self.location()));
+    }
+
+    Ok(DecodeReport {
+      meta: self.meta,
+      end_marker,
+      pixels_decoded: self.pixel_index,
+      error: None,
+      trailing_bytes: None,
+      has_trailing_data,
+    })
+  }
+
+  // Decodes the next pixel of the image, or returns `None` once all pixels
+  // have been decoded.
+  #[inline]
+  pub(crate) fn next_pixel(&mut self) -> Result<Option<Pixel>, Error> {
+    self.next_pixel_observed(&mut ())
+  }
+
+  // Decodes the next pixel of the image like `next_pixel`, passing each
+  // decoded `Op` to the given observer.
+  #[inline]
+  pub(crate) fn next_pixel_observed<O: Observer>(
+    &mut self,
+    observer: &mut O,
+  ) -> Result<Option<Pixel>, Error> {
+    if self.is_done() {
+      return Ok(None);
+    }
+
+    if self.recovered.is_some() {
+      return Ok(self.fill_pixel());
+    }
+
+    match self.decode_pixel(observer) {
+      Ok(pixel) => Ok(Some(pixel)),
+      Err(e) => self.recover(e),
+    }
+  }
+
+  // Returns the output pixels are written with.
+  pub(crate) fn output(&self) -> &Output {
+    &self.output
+  }
+
+  // Decodes and discards the given number of pixels.
+  pub(crate) fn skip_pixels(&mut self, count: u64) -> Result<(), Error> {
+    for _ in 0..count {
+      self.next_pixel()?;
+    }
+
+    Ok(())
+  }
+
+  // Whether all pixels have been decoded, or decoding was stopped after
+  // recovering from an error.
+  #[inline]
+  fn is_done(&self) -> bool {
+    self.pixel_index == self.meta.num_pixels()
+      || (self.recovered.is_some() && self.recovery == Some(Recovery::Truncate))
+  }
+
+  // Recovers from the given error as configured by the options, returning
+  // the pixel to use in place of the pixel that failed to decode. Without a
+  // recovery option, or if the error isn't in the pixel data itself, such as
+  // an IO error, the error is returned.
+  #[cold]
+  fn recover(&mut self, err: Error) -> Result<Option<Pixel>, Error> {
+    if self.recovery.is_none() || !err.is_recoverable() {
+      return Err(err);
+    }
+
+    self.recovered = Some((self.pixel_index, err));
+
+    Ok(self.fill_pixel())
+  }
+
+  // Returns the fill pixel in place of a pixel that couldn't be decoded, or
+  // `None` if decoding stops instead.
+  fn fill_pixel(&mut self) -> Option<Pixel> {
+    match self.recovery {
+      Some(Recovery::Fill(pixel)) => {
+        self.pixel_index += 1;
+        Some(pixel)
+      }
+      _ => None,
+    }
+  }
+
+  // Decodes the next pixel of the image, reading more of the encoded image
+  // only when the pixel isn't part of a run.
+  #[inline]
+  fn decode_pixel<O: Observer>(&mut self, observer: &mut O) -> Result<Pixel, Error> {
+    let pixel = if self.state.run_count > 0 {
+      decode_pixel(&mut self.state, &[], &mut 0)?
+    } else {
+      let mut pos = 0;
+      let bytes = self.input.fill(Op::MAX_LEN)?;
+      let pixel = decode_pixel(&mut self.state, bytes, &mut pos)
+        .map_err(|e| e.at(self.location()))?;
+
+      self.input.consume(pos);
+      self.offset += pos as u64;
+
+      if let Some(kind) = self.state.last_op {
+        observer.observe_op(kind, pos, &self.state);
+      }
+
+      pixel
+    };
+
+    self.pixel_index += 1;
+
+    Ok(pixel)
+  }
+
+  // Returns the location of the next `Op` to decode, or of the end marker
+  // once all pixels have been decoded.
+  fn location(&self) -> ErrorLocation {
+    ErrorLocation::new(&self.meta, self.offset, self.pixel_index, self.state.last_op)
+  }
+}
+
+/// An iterator that lazily decodes the pixels of an image, created with
+/// `decode_pixels` or `Decoder::into_pixels`.
+/// 
+/// Each item is either the next decoded pixel or the error that occurred
+/// while decoding it. The iterator ends after all pixels have been decoded,
+/// or after the first error. The end marker isn't read.
+pub struct Pixels<R> {
+  // The underlying decoder, dropped after an error.
+  decoder: Option<Decoder<R>>,
+}
+
+impl<R: io::Read> Iterator for Pixels<R> {
+  type Item = Result<Pixel, Error>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    match self.decoder.as_mut()?.next_pixel() {
+      Ok(pixel) => pixel.map(Ok),
+      Err(e) => {
+        self.decoder = None;
+        Some(Err(e))
+      }
+    }
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    match &self.decoder {
+      Some(decoder) => {
+        let remaining = decoder.meta.num_pixels() - decoder.pixel_index;
+        (0, usize::try_from(remaining).ok())
+      }
+      None => (0, Some(0)),
+    }
+  }
+}
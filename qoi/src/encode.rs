@@ -1,9 +1,26 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
 use std::io;
 
+#[cfg(feature = "std")]
+use crate::buffer::{ReadBuffer, WriteBuffer};
 use crate::error::Error;
-use crate::meta::{ImageMeta, QOI_BYTES_END, QOI_BYTES_MAGIC, QOI_MAX_RUN};
+use crate::meta::{
+  decoded_len,
+  validate_channels,
+  ImageMeta,
+  QOI_BYTES_END,
+  QOI_BYTES_MAGIC,
+  QOI_MAX_RUN,
+};
 use crate::op::Op;
+use crate::options::EncodeOptions;
+#[cfg(feature = "std")]
+use crate::options::SupportedOptions;
 use crate::pixel::{Pixel, PixelDiff};
+use crate::progress::ProgressTracker;
+use crate::sink::{Sink, SliceSink};
 use crate::state::State;
 
 /// Encodes an image's raw pixel data and `ImageMeta` data into a QOI encoded
@@ -14,65 +31,285 @@ use crate::state::State;
 /// image's pixel data, and `std::io::Write` for the encoded image's
 /// destination.
 /// 
-/// Note that this function performs frequent reads and writes, so it's
-/// recommended to provide a buffered IO implementation such as
-/// `std::io::BufReader` and `std::io::BufWriter` for streaming applications.
+/// Reads and writes are buffered internally, so there's no need to provide a
+/// buffered IO implementation such as `std::io::BufReader`. The `reader` is
+/// never read past the end of the image's pixel data.
+/// 
+/// `Error::UnexpectedEof` is returned if the `reader` ends before all pixels
+/// have been read.
+#[cfg(feature = "std")]
 pub fn encode_image<R: io::Read, W: io::Write>(
-  mut reader: R,
-  mut writer: W,
+  reader: R,
+  writer: W,
+  meta: &ImageMeta,
+) -> Result<(), Error> {
+  encode_image_with(reader, writer, meta, &EncodeOptions::default())
+}
+
+/// Encodes an image's raw pixel data and `ImageMeta` data into a QOI encoded
+/// image, customized with the given `EncodeOptions`.
+/// 
+/// See `encode_image` for details.
+#[cfg(feature = "std")]
+pub fn encode_image_with<R: io::Read, W: io::Write>(
+  reader: R,
+  writer: W,
   meta: &ImageMeta,
+  options: &EncodeOptions,
 ) -> Result<(), Error> {
-  encode_header(meta, &mut writer)?;
+  options.check_supported(SupportedOptions::PROGRESS | SupportedOptions::UNPREMULTIPLY)?;
+
+  let mut sink = WriteBuffer::new(writer);
+
+  encode_header(meta, &mut sink)?;
 
+  let channels = meta.channels as usize;
+  let pixel_data_len = meta.num_pixels().saturating_mul(channels as u64);
+  let mut input = ReadBuffer::new(reader.take(pixel_data_len));
   let mut state = State::new();
-  let mut pixel_buf = vec![0; meta.channels as usize];
+  let mut progress = ProgressTracker::new(options.progress, meta);
+  let mut pixels_remaining = meta.num_pixels();
+  let unpremultiply = unpremultiply(meta, options);
 
-  for _ in 0..meta.num_pixels() {
-    reader.read_exact(&mut pixel_buf)?;
+  while pixels_remaining > 0 {
+    let bytes = input.fill(channels)?;
 
-    let pixel = Pixel {
-      r: pixel_buf[0],
-      g: pixel_buf[1],
-      b: pixel_buf[2],
-      a: pixel_buf.get(3).copied().unwrap_or(state.prev_pixel.a),
-    };
+    if bytes.len() < channels {
+      return Err(Error::UnexpectedEof(None));
+    }
 
-    encode_pixel(&mut state, pixel, &mut writer)?;
-    state.prev_pixel = pixel;
+    // Encode every complete pixel that's buffered at once, the reader is
+    // limited to the image's pixel data so there are never too many.
+    let len = bytes.len() - bytes.len() % channels;
+
+    encode_pixels(&mut state, &bytes[..len], channels, unpremultiply, &mut sink)?;
+    input.consume(len);
+    pixels_remaining -= (len / channels) as u64;
+    progress.update(meta.num_pixels() - pixels_remaining)?;
   }
 
-  if state.run_count > 0 {
-    Op::Run(state.run_count).into_bytes(&mut writer)?;
+  encode_end(&mut state, &mut sink)?;
+  sink.flush()?;
+
+  Ok(())
+}
+
+/// Encodes an image's raw pixel data like `encode_image_with`, from a
+/// `reader` that can seek. This allows rows to be read out of order, so when
+/// `EncodeOptions::flip_vertically` is `true`, the pixel data's rows are read
+/// bottom-up, starting from the reader's current position. The reader is
+/// left positioned at the end of the pixel data.
+#[cfg(feature = "std")]
+pub fn encode_image_seekable<R: io::Read + io::Seek, W: io::Write>(
+  mut reader: R,
+  writer: W,
+  meta: &ImageMeta,
+  options: &EncodeOptions,
+) -> Result<(), Error> {
+  if !options.flip_vertically {
+    return encode_image_with(reader, writer, meta, options);
+  }
+
+  let mut sink = WriteBuffer::new(writer);
+
+  encode_header(meta, &mut sink)?;
+
+  let channels = meta.channels as usize;
+  let row_len = decoded_len(meta.width as u64, channels).ok_or(Error::InvalidDimensions)?;
+  let start = reader.stream_position()?;
+  let mut row = vec![0; row_len];
+  let mut state = State::new();
+  let mut progress = ProgressTracker::new(options.progress, meta);
+  let unpremultiply = unpremultiply(meta, options);
+
+  for y in (0..meta.height as u64).rev() {
+    reader.seek(io::SeekFrom::Start(start + y * row_len as u64))?;
+    reader.read_exact(&mut row).map_err(|err| match err.kind() {
+      io::ErrorKind::UnexpectedEof => Error::UnexpectedEof(None),
+      _ => err.into(),
+    })?;
+    encode_pixels(&mut state, &row, channels, unpremultiply, &mut sink)?;
+    progress.update((meta.height as u64 - y) * meta.width as u64)?;
   }
 
-  writer.write_all(&QOI_BYTES_END)?;
-  writer.flush()?;
+  reader.seek(io::SeekFrom::Start(start + meta.height as u64 * row_len as u64))?;
+
+  encode_end(&mut state, &mut sink)?;
+  sink.flush()?;
+
+  Ok(())
+}
+
+/// Encodes an image's raw pixel data held in memory into the provided `dest`
+/// slice, returning the number of bytes written.
+/// 
+/// This function doesn't allocate and is available without the `std` and
+/// `alloc` features. The encoded size isn't known up front, so encoding
+/// fails with `Error::BufferTooSmall` only once `dest` runs out, reporting
+/// `ImageMeta::max_encoded_len` as the length required, which is always
+/// enough. A `dest` of that length never runs out, but most images fit in far
+/// less.
+pub fn encode_to_slice(source: &[u8], dest: &mut [u8], meta: &ImageMeta) -> Result<usize, Error> {
+  encode_to_slice_with(source, dest, meta, &EncodeOptions::default())
+}
+
+/// Encodes an image's raw pixel data held in memory into the provided `dest`
+/// slice, customized with the given `EncodeOptions`, returning the number of
+/// bytes written.
+/// 
+/// See `encode_to_slice` for details.
+pub fn encode_to_slice_with(
+  source: &[u8],
+  dest: &mut [u8],
+  meta: &ImageMeta,
+  options: &EncodeOptions,
+) -> Result<usize, Error> {
+  validate_channels(meta.channels)?;
+
+  let max_len = meta.max_encoded_len().ok_or(Error::InvalidDimensions)?;
+  let mut sink = SliceSink::new(dest, max_len);
+  encode_slice(source, &mut sink, meta, options)?;
+
+  Ok(sink.len())
+}
+
+/// Encodes an image's raw pixel data held in memory into a newly allocated
+/// `Vec`.
+/// 
+/// Requires the `alloc` feature, which is enabled by default through `std`.
+#[cfg(feature = "alloc")]
+pub fn encode_to_vec(source: &[u8], meta: &ImageMeta) -> Result<Vec<u8>, Error> {
+  encode_to_vec_with(source, meta, &EncodeOptions::default())
+}
+
+/// Encodes an image's raw pixel data held in memory into a newly allocated
+/// `Vec`, customized with the given `EncodeOptions`.
+/// 
+/// Requires the `alloc` feature, which is enabled by default through `std`.
+#[cfg(feature = "alloc")]
+pub fn encode_to_vec_with(
+  source: &[u8],
+  meta: &ImageMeta,
+  options: &EncodeOptions,
+) -> Result<Vec<u8>, Error> {
+  let mut dest = Vec::new();
+  encode_slice(source, &mut dest, meta, options)?;
+  Ok(dest)
+}
+
+// Encodes the raw pixel data held in `source` and writes the encoded image to
+// the given sink.
+fn encode_slice<S: Sink>(
+  source: &[u8],
+  sink: &mut S,
+  meta: &ImageMeta,
+  options: &EncodeOptions,
+) -> Result<(), Error> {
+  encode_header(meta, sink)?;
+
+  let source_len = meta.decoded_len().ok_or(Error::InvalidDimensions)?;
+  let source = source.get(..source_len).ok_or(Error::UnexpectedEof(None))?;
+  let channels = meta.channels as usize;
+  let row_len = decoded_len(meta.width as u64, channels).ok_or(Error::InvalidDimensions)?;
+  let height = meta.height as usize;
+  let mut state = State::new();
+  let mut progress = ProgressTracker::new(options.progress, meta);
+  let unpremultiply = unpremultiply(meta, options);
+
+  for y in 0..height {
+    let row = if options.flip_vertically { height - 1 - y } else { y };
+    let pixels = &source[row * row_len..(row + 1) * row_len];
+
+    encode_pixels(&mut state, pixels, channels, unpremultiply, sink)?;
+    progress.update((y as u64 + 1) * meta.width as u64)?;
+  }
+
+  encode_end(&mut state, sink)
+}
+
+// Encodes the raw pixel data held in `pixels`, continuing from the given
+// `state`.
+fn encode_pixels<S: Sink>(
+  state: &mut State,
+  pixels: &[u8],
+  channels: usize,
+  unpremultiply: bool,
+  sink: &mut S,
+) -> Result<(), Error> {
+  for pixel_buf in pixels.chunks_exact(channels) {
+    let pixel = read_pixel_with(state, pixel_buf, unpremultiply);
+
+    encode_pixel(state, pixel, sink)?;
+    state.prev_pixel = pixel;
+  }
 
   Ok(())
 }
 
 // Attempts to encode the image's header and write the encoded bytes to the
 // image's destination.
-fn encode_header<W: io::Write>(meta: &ImageMeta, mut writer: W) -> Result<(), Error> {
-  writer.write_all(QOI_BYTES_MAGIC)?;
-  writer.write_all(&meta.width.to_be_bytes())?;
-  writer.write_all(&meta.height.to_be_bytes())?;
-  writer.write_all(&[meta.channels, meta.colorspace as u8])?;
+pub(crate) fn encode_header<S: Sink>(meta: &ImageMeta, sink: &mut S) -> Result<(), Error> {
+  validate_channels(meta.channels)?;
+  sink.write_bytes(QOI_BYTES_MAGIC)?;
+  sink.write_bytes(&meta.width.to_be_bytes())?;
+  sink.write_bytes(&meta.height.to_be_bytes())?;
+  sink.write_bytes(&[meta.channels, meta.colorspace as u8])?;
   Ok(())
 }
 
+// Reads a pixel from the given raw pixel bytes, which contain either three or
+// four channels. Pixels without an alpha channel inherit the alpha value of
+// the previous pixel.
+pub(crate) fn read_pixel(state: &State, pixel_buf: &[u8]) -> Pixel {
+  Pixel {
+    r: pixel_buf[0],
+    g: pixel_buf[1],
+    b: pixel_buf[2],
+    a: pixel_buf.get(3).copied().unwrap_or(state.prev_pixel.a),
+  }
+}
+
+// Reads a pixel like `read_pixel`, additionally dividing out premultiplied
+// alpha if requested.
+fn read_pixel_with(state: &State, pixel_buf: &[u8], unpremultiply: bool) -> Pixel {
+  let pixel = read_pixel(state, pixel_buf);
+
+  if unpremultiply {
+    pixel.unpremultiply()
+  } else {
+    pixel
+  }
+}
+
+// Whether pixels need their premultiplied alpha divided out, which only
+// applies to images with an alpha channel.
+fn unpremultiply(meta: &ImageMeta, options: &EncodeOptions) -> bool {
+  options.unpremultiply && meta.channels == 4
+}
+
+// Attempts to encode and write the end of the image, any pending run followed
+// by the end marker.
+pub(crate) fn encode_end<S: Sink>(state: &mut State, sink: &mut S) -> Result<(), Error> {
+  if state.run_count > 0 {
+    Op::Run(state.run_count).into_bytes(sink)?;
+    state.run_count = 0;
+  }
+
+  sink.write_bytes(&QOI_BYTES_END)
+}
+
 // Attempts to encode and write the provided pixel using the QOI OP encoding
 // scheme and provided `state`.
-fn encode_pixel<W: io::Write>(
+pub(crate) fn encode_pixel<S: Sink>(
   state: &mut State,
   pixel: Pixel,
-  mut writer: W,
+  sink: &mut S,
 ) -> Result<(), Error> {
   if pixel == state.prev_pixel {
     state.run_count += 1;
 
     if state.run_count == QOI_MAX_RUN {
-      Op::Run(QOI_MAX_RUN).into_bytes(&mut writer)?;
+      Op::Run(QOI_MAX_RUN).into_bytes(sink)?;
       state.run_count = 0;
     }
 
@@ -80,22 +317,22 @@ fn encode_pixel<W: io::Write>(
   }
 
   if state.run_count > 0 {
-    Op::Run(state.run_count).into_bytes(&mut writer)?;
+    Op::Run(state.run_count).into_bytes(sink)?;
     state.run_count = 0;
   }
 
   if let Some(index) = state.cache_match_or_replace(pixel) {
-    Op::Index(index as u8).into_bytes(&mut writer)?;
+    Op::Index(index as u8).into_bytes(sink)?;
     return Ok(());
   }
 
   if let Some(diff) = pixel.diff(&state.prev_pixel) {
     match diff {
       PixelDiff::Color(diff_r, diff_g, diff_b) => {
-        Op::Color(diff_r, diff_g, diff_b).into_bytes(&mut writer)?;
+        Op::Color(diff_r, diff_g, diff_b).into_bytes(sink)?;
       }
       PixelDiff::Luma(luma_g, luma_rg, luma_bg) => {
-        Op::Luma(luma_g, luma_rg, luma_bg).into_bytes(&mut writer)?;
+        Op::Luma(luma_g, luma_rg, luma_bg).into_bytes(sink)?;
       }
     }
 
@@ -103,11 +340,11 @@ fn encode_pixel<W: io::Write>(
   }
 
   if pixel.a == state.prev_pixel.a {
-    Op::Rgb(pixel.r, pixel.g, pixel.b).into_bytes(&mut writer)?;
+    Op::Rgb(pixel.r, pixel.g, pixel.b).into_bytes(sink)?;
     return Ok(());
   }
 
-  Op::Rgba(pixel.r, pixel.g, pixel.b, pixel.a).into_bytes(&mut writer)?;
+  Op::Rgba(pixel.r, pixel.g, pixel.b, pixel.a).into_bytes(sink)?;
   Ok(())
 }
 
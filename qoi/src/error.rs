@@ -1,37 +1,168 @@
-use std::array;
+use core::array;
+use core::fmt;
+#[cfg(feature = "std")]
 use std::error;
-use std::fmt;
+#[cfg(feature = "std")]
 use std::io;
 
+use crate::meta::ImageMeta;
+use crate::op::OpKind;
+
 /// An enumeration of all error values this crate may produce.
 pub enum Error {
+  /// Failed to decode or encode an image into a buffer that's too small to
+  /// hold the output, contains the length required, in bytes, or in values
+  /// for `f32` buffers.
+  BufferTooSmall(usize),
+  /// Decoding or encoding was cancelled by a `Progress` callback.
+  Cancelled,
+  /// Failed to decode or encode an image with an unsupported number of color
+  /// channels, only 3 (RGB) and 4 (RGBA) are supported.
+  InvalidChannels(u8),
   /// Failed to derive a supported colorspace from a QOI image.
   InvalidColorspace(u8),
-  /// Failed to decode a QOI image with invalid image dimensions.
+  /// Failed to decode a QOI image with invalid image dimensions, or with
+  /// dimensions too large to be decoded into memory on the current platform.
   InvalidDimensions,
   /// Failed to decode a QOI image with a missing or malformed header.
   InvalidHeader,
+  /// Failed to strictly decode a QOI image because the bytes following its
+  /// last pixel aren't a valid end marker. Contains the location of the end
+  /// marker.
+  InvalidEndMarker(ErrorLocation),
   /// Failed to decode an index op (Op::Index) because the index value is
-  /// greater than the max of 64.
-  InvalidIndex(u8),
+  /// greater than the max of 64. Contains the index and where it was found.
+  InvalidIndex(u8, ErrorLocation),
+  /// Failed to decode a region of an image because the given `Rect` isn't
+  /// entirely within the image.
+  InvalidRect,
+  /// Failed to decode an image into a buffer because the given row stride is
+  /// shorter than a row of decoded pixels.
+  InvalidStride,
   /// Any `std::io::Error` that occurs during decoding or encoding. Typically
   /// these will arise from problems with reading an image source or writing to
-  /// an image destination.
+  /// an image destination. Requires the `std` feature.
+  #[cfg(feature = "std")]
   IoError(io::Error),
+  /// Failed to decode an image that exceeds the configured `DecodeLimits`.
+  LimitExceeded,
+  /// Failed to decode an image with alpha into a `PixelFormat` without alpha,
+  /// such as `PixelFormat::Rgb`, because no `DecodeOptions::background` was
+  /// given to composite it onto. Alpha is never dropped silently.
+  MissingBackground,
+  /// Failed to strictly decode a QOI image because more data follows its end
+  /// marker. Contains the location of the first byte after the end marker.
+  TrailingData(ErrorLocation),
   /// Unexpectedly reached the end of an image source before decoding or
-  /// encoding was completed.
-  UnexpectedEof,
+  /// encoding was completed. When decoding, contains the location of the
+  /// incomplete header, op, or end marker. It's `None` when encoding, or when
+  /// an image is rejected up front because its header claims more pixels
+  /// than the image could possibly contain.
+  UnexpectedEof(Option<ErrorLocation>),
   /// Encountered an unknown QOI encoding chunk, or `Op`, while decoding a OQI
-  /// image.
-  UnknownTag(u8),
+  /// image. Contains the chunk's tag and where it was found.
+  UnknownTag(u8, ErrorLocation),
+  /// Failed to decode or encode an image because an option was set that the
+  /// function called doesn't support, such as `DecodeOptions::stride` when
+  /// decoding to an IO stream. Contains the name of the option.
+  UnsupportedOption(&'static str),
+}
+
+/// The location in an encoded QOI image at which decoding failed, to help
+/// track down corrupt or truncated images.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ErrorLocation {
+  /// The byte offset from the start of the encoded image, including its
+  /// header, of the header, op, or end marker that failed to decode, or of
+  /// the unexpected data that follows the end marker.
+  pub offset: u64,
+  /// The x and y position of the pixel that failed to decode. `None` when
+  /// decoding the header or the end marker.
+  pub pixel: Option<(u32, u32)>,
+  /// The kind of the last op that was successfully decoded, if any.
+  pub last_op: Option<OpKind>,
+}
+
+impl ErrorLocation {
+  // Creates the location of the header, which starts the image.
+  pub(crate) fn header() -> Self {
+    Self::default()
+  }
+
+  // Creates the location of the op that starts at `offset` and decodes the
+  // pixel at `pixel_index`. Once all of the image's pixels have been decoded,
+  // this is the location of the end marker instead.
+  pub(crate) fn new(meta: &ImageMeta, offset: u64, pixel_index: u64, last_op: Option<OpKind>) -> Self {
+    let width = meta.width as u64;
+    let pixel = if pixel_index < meta.num_pixels() {
+      Some(((pixel_index % width) as u32, (pixel_index / width) as u32))
+    } else {
+      None
+    };
+
+    Self { offset, pixel, last_op }
+  }
+}
+
+impl fmt::Display for ErrorLocation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "at byte {}", self.offset)?;
+
+    if let Some((x, y)) = self.pixel {
+      write!(f, ", pixel ({}, {})", x, y)?;
+    }
+
+    if let Some(last_op) = self.last_op {
+      write!(f, ", after {:?} op", last_op)?;
+    }
+
+    Ok(())
+  }
+}
+
+impl Error {
+  // Attaches the given location to errors that describe where decoding
+  // failed, other errors are returned unchanged.
+  pub(crate) fn at(self, location: ErrorLocation) -> Self {
+    match self {
+      Error::InvalidEndMarker(_) => Error::InvalidEndMarker(location),
+      Error::InvalidIndex(index, _) => Error::InvalidIndex(index, location),
+      Error::TrailingData(_) => Error::TrailingData(location),
+      Error::UnexpectedEof(_) => Error::UnexpectedEof(Some(location)),
+      Error::UnknownTag(byte, _) => Error::UnknownTag(byte, location),
+      err => err,
+    }
+  }
+
+  // Whether decoding can recover from the error with
+  // `DecodeOptions::recovery`, which is the case for errors in the encoded
+  // pixel data, but not for errors reading it.
+  pub(crate) fn is_recoverable(&self) -> bool {
+    matches!(self, Error::InvalidIndex(..) | Error::UnexpectedEof(_) | Error::UnknownTag(..))
+  }
 }
 
+#[cfg(feature = "std")]
 impl From<io::Error> for Error {
   fn from(io_err: io::Error) -> Self {
     Error::IoError(io_err)
   }
 }
 
+/// Converts an `Error` into a `std::io::Error`, for use in IO adapters such
+/// as `DecoderReader`. An `Error::IoError` is unwrapped, all other errors are
+/// wrapped as `UnexpectedEof` or `InvalidData`.
+#[cfg(feature = "std")]
+impl From<Error> for io::Error {
+  fn from(err: Error) -> Self {
+    match err {
+      Error::IoError(io_err) => io_err,
+      Error::UnexpectedEof(_) => io::Error::new(io::ErrorKind::UnexpectedEof, err),
+      _ => io::Error::new(io::ErrorKind::InvalidData, err),
+    }
+  }
+}
+
 impl From<array::TryFromSliceError> for Error {
   fn from(_: array::TryFromSliceError) -> Self {
     Error::InvalidDimensions
@@ -41,26 +172,60 @@ impl From<array::TryFromSliceError> for Error {
 impl fmt::Display for Error {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     match self {
+      Error::BufferTooSmall(len) => {
+        write!(f, "buffer is too small to hold the image, a length of {} is required", len)
+      }
+      Error::Cancelled => {
+        write!(f, "decoding or encoding was cancelled")
+      }
+      Error::InvalidChannels(channels) => {
+        write!(f, "invalid number of channels {}, expected 3 for RGB or 4 for RGBA", channels)
+      }
       Error::InvalidColorspace(byte) => {
         write!(f, "invalid image colorspace {}, expected 0 for sRGB or 1 for linear", byte)
       }
       Error::InvalidDimensions => {
         write!(f, "invalid image width or height")
       }
+      Error::InvalidEndMarker(location) => {
+        write!(f, "invalid QOI image end marker {}", location)
+      }
       Error::InvalidHeader => {
         write!(f, "invalid or malformed QOI image header")
       }
-      Error::InvalidIndex(index) => {
-        write!(f, "invalid index {}", index)
+      Error::InvalidIndex(index, location) => {
+        write!(f, "invalid index {} {}", index, location)
+      }
+      Error::InvalidRect => {
+        write!(f, "rectangle is not within the image")
+      }
+      Error::InvalidStride => {
+        write!(f, "stride is shorter than a row of pixels")
       }
+      #[cfg(feature = "std")]
       Error::IoError(io_err) => {
         write!(f, "{}", io_err)
       }
-      Error::UnexpectedEof => {
+      Error::LimitExceeded => {
+        write!(f, "image exceeds the configured decode limits")
+      }
+      Error::MissingBackground => {
+        write!(f, "alpha can't be dropped without a background to composite it onto")
+      }
+      Error::TrailingData(location) => {
+        write!(f, "unexpected data after the QOI image end marker {}", location)
+      }
+      Error::UnexpectedEof(None) => {
         write!(f, "unexpectedly reached end of file before decoding or encoding was completed")
       }
-      Error::UnknownTag(byte) => {
-        write!(f, "unknown encoding `{:b}`", byte)
+      Error::UnexpectedEof(Some(location)) => {
+        write!(f, "unexpectedly reached end of file before decoding was completed, {}", location)
+      }
+      Error::UnknownTag(byte, location) => {
+        write!(f, "unknown encoding `{:b}` {}", byte, location)
+      }
+      Error::UnsupportedOption(name) => {
+        write!(f, "the `{}` option is not supported by this function", name)
       }
     }
   }
@@ -72,6 +237,7 @@ impl fmt::Debug for Error {
   }
 }
 
+#[cfg(feature = "std")]
 impl error::Error for Error {
   fn source(&self) -> Option<&(dyn error::Error + 'static)> {
     match self {
@@ -81,17 +247,30 @@ impl error::Error for Error {
   }
 }
 
-#[cfg(test)]
+/// Compares errors by their variant and contents. Since `std::io::Error`
+/// can't be compared, two `Error::IoError` values are equal if their
+/// `std::io::ErrorKind` is.
 impl PartialEq for Error {
   fn eq(&self, other: &Self) -> bool {
     match (self, other) {
+      (Error::BufferTooSmall(a), Error::BufferTooSmall(b)) => a == b,
+      (Error::Cancelled, Error::Cancelled) => true,
+      (Error::InvalidChannels(a), Error::InvalidChannels(b)) => a == b,
       (Error::InvalidColorspace(a), Error::InvalidColorspace(b)) => a == b,
       (Error::InvalidDimensions, Error::InvalidDimensions) => true,
+      (Error::InvalidEndMarker(a), Error::InvalidEndMarker(b)) => a == b,
       (Error::InvalidHeader, Error::InvalidHeader) => true,
-      (Error::InvalidIndex(a), Error::InvalidIndex(b)) => a == b,
-      (Error::IoError(..), Error::IoError(..)) => true,
-      (Error::UnexpectedEof, Error::UnexpectedEof) => true,
-      (Error::UnknownTag(a), Error::UnknownTag(b)) => a == b,
+      (Error::InvalidIndex(a, a_loc), Error::InvalidIndex(b, b_loc)) => a == b && a_loc == b_loc,
+      (Error::InvalidRect, Error::InvalidRect) => true,
+      (Error::InvalidStride, Error::InvalidStride) => true,
+      #[cfg(feature = "std")]
+      (Error::IoError(a), Error::IoError(b)) => a.kind() == b.kind(),
+      (Error::LimitExceeded, Error::LimitExceeded) => true,
+      (Error::MissingBackground, Error::MissingBackground) => true,
+      (Error::TrailingData(a), Error::TrailingData(b)) => a == b,
+      (Error::UnexpectedEof(a), Error::UnexpectedEof(b)) => a == b,
+      (Error::UnknownTag(a, a_loc), Error::UnknownTag(b, b_loc)) => a == b && a_loc == b_loc,
+      (Error::UnsupportedOption(a), Error::UnsupportedOption(b)) => a == b,
       _ => false,
     }
   }
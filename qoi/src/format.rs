@@ -0,0 +1,180 @@
+use crate::error::Error;
+use crate::meta::{Colorspace, ImageMeta};
+use crate::options::DecodeOptions;
+use crate::pixel::Pixel;
+use crate::srgb::unorm_to_f32;
+
+/// The layout of a decoded pixel's channels in memory. Each channel is a
+/// single byte.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PixelFormat {
+  /// Red, green, and blue channels. Images with alpha are composited onto
+  /// `DecodeOptions::background`, which is then required.
+  Rgb,
+  /// Red, green, blue, and alpha channels.
+  Rgba,
+  /// Blue, green, red, and alpha channels.
+  Bgra,
+  /// Alpha, red, green, and blue channels.
+  Argb,
+  /// A single luma channel, derived from the red, green, and blue channels
+  /// using the Rec. 601 weights. Images with alpha are composited onto
+  /// `DecodeOptions::background`, which is then required.
+  Gray,
+  /// A luma channel followed by an alpha channel.
+  GrayAlpha,
+}
+
+impl PixelFormat {
+  /// Returns the pixel format that matches the given number of channels as
+  /// found in an image's header, `Rgb` for 3 and `Rgba` for 4.
+  pub fn from_channels(channels: u8) -> Result<Self, Error> {
+    match channels {
+      3 => Ok(PixelFormat::Rgb),
+      4 => Ok(PixelFormat::Rgba),
+      _ => Err(Error::InvalidChannels(channels)),
+    }
+  }
+
+  /// Returns `true` if this format has an alpha channel.
+  pub fn has_alpha(&self) -> bool {
+    !matches!(self, PixelFormat::Rgb | PixelFormat::Gray)
+  }
+
+  /// Returns the number of bytes a single pixel occupies in this format.
+  pub fn bytes_per_pixel(&self) -> usize {
+    match self {
+      PixelFormat::Gray => 1,
+      PixelFormat::GrayAlpha => 2,
+      PixelFormat::Rgb => 3,
+      PixelFormat::Rgba | PixelFormat::Bgra | PixelFormat::Argb => 4,
+    }
+  }
+
+  // Writes the given pixel into the start of `buf` in this format. `buf` must
+  // be at least `bytes_per_pixel` long.
+  pub(crate) fn write_pixel(&self, pixel: Pixel, buf: &mut [u8]) {
+    let Pixel { r, g, b, a } = pixel;
+
+    match self {
+      PixelFormat::Rgb => buf[..3].copy_from_slice(&[r, g, b]),
+      PixelFormat::Rgba => buf[..4].copy_from_slice(&[r, g, b, a]),
+      PixelFormat::Bgra => buf[..4].copy_from_slice(&[b, g, r, a]),
+      PixelFormat::Argb => buf[..4].copy_from_slice(&[a, r, g, b]),
+      PixelFormat::Gray => buf[0] = luma(pixel),
+      PixelFormat::GrayAlpha => buf[..2].copy_from_slice(&[luma(pixel), a]),
+    }
+  }
+
+  // Writes the given red, green, blue, and alpha values into the start of
+  // `values` in this format, like `write_pixel`. Luma is computed from the
+  // values as they are, without rounding.
+  pub(crate) fn write_f32_pixel(&self, rgba: [f32; 4], values: &mut [f32]) {
+    let [r, g, b, a] = rgba;
+    let luma = || r * 0.299 + g * 0.587 + b * 0.114;
+
+    match self {
+      PixelFormat::Rgb => values[..3].copy_from_slice(&[r, g, b]),
+      PixelFormat::Rgba => values[..4].copy_from_slice(&[r, g, b, a]),
+      PixelFormat::Bgra => values[..4].copy_from_slice(&[b, g, r, a]),
+      PixelFormat::Argb => values[..4].copy_from_slice(&[a, r, g, b]),
+      PixelFormat::Gray => values[0] = luma(),
+      PixelFormat::GrayAlpha => values[..2].copy_from_slice(&[luma(), a]),
+    }
+  }
+}
+
+// Writes decoded pixels in the pixel format chosen by the decode options,
+// converting them as configured by the options first.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Output {
+  // The opaque background pixels are composited onto, only when alpha would
+  // otherwise be dropped.
+  background: Option<Pixel>,
+  colorspace: Colorspace,
+  pub format: PixelFormat,
+  premultiply: bool,
+}
+
+impl Output {
+  // Determines the output for the image described by `meta` from the given
+  // options, falling back to the image's number of channels. Alpha is never
+  // dropped silently, so an image with alpha requires a background when its
+  // format has none.
+  pub fn new(meta: &ImageMeta, options: &DecodeOptions) -> Result<Self, Error> {
+    let format = match options.format {
+      Some(format) => format,
+      None => PixelFormat::from_channels(meta.channels)?,
+    };
+
+    let background = if meta.channels == 3 || format.has_alpha() {
+      None
+    } else {
+      Some(options.background.ok_or(Error::MissingBackground)?)
+    };
+
+    Ok(Self {
+      background,
+      colorspace: meta.colorspace,
+      format,
+      premultiply: options.premultiply && meta.channels == 4,
+    })
+  }
+
+  // Whether pixels are written as they are, without compositing or
+  // premultiplying them.
+  #[inline]
+  pub fn is_plain(&self) -> bool {
+    self.background.is_none() && !self.premultiply
+  }
+
+  // Converts the given pixel and writes it into the start of `buf`, see
+  // `PixelFormat::write_pixel`.
+  #[inline]
+  pub fn write_pixel(&self, pixel: Pixel, buf: &mut [u8]) {
+    let pixel = match self.background {
+      Some(background) => pixel.composite(background, self.colorspace),
+      None if self.premultiply => pixel.premultiply(),
+      None => pixel,
+    };
+
+    self.format.write_pixel(pixel, buf);
+  }
+
+  // Converts the color channels of the given pixel to linear light `f32`
+  // values with `to_linear`, then converts the pixel like `write_pixel` and
+  // writes it into the start of `values`. Compositing and premultiplying
+  // happen on the linear values, so nothing is rounded to 8 bits.
+  #[inline]
+  pub fn write_linear_pixel(&self, pixel: Pixel, to_linear: fn(u8) -> f32, values: &mut [f32]) {
+    let mut alpha = unorm_to_f32(pixel.a);
+    let mut rgb = [pixel.r, pixel.g, pixel.b].map(to_linear);
+
+    match self.background {
+      Some(background) => {
+        let background = [background.r, background.g, background.b].map(to_linear);
+
+        for (c, background) in rgb.iter_mut().zip(background) {
+          *c = *c * alpha + background * (1.0 - alpha);
+        }
+
+        alpha = 1.0;
+      }
+      None if self.premultiply => rgb.iter_mut().for_each(|c| *c *= alpha),
+      None => {}
+    }
+
+    let [r, g, b] = rgb;
+    self.format.write_f32_pixel([r, g, b, alpha], values);
+  }
+}
+
+// Computes the Rec. 601 luma of the given pixel, rounded to the nearest
+// integer.
+fn luma(pixel: Pixel) -> u8 {
+  let r = pixel.r as u32;
+  let g = pixel.g as u32;
+  let b = pixel.b as u32;
+
+  ((r * 299 + g * 587 + b * 114 + 500) / 1000) as u8
+}
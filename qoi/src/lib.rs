@@ -6,9 +6,81 @@
 //! structures by accepting a generic trait bound of `std::io::Read` for the
 //! image source, and `std::io::Write` for the image destination.
 //! 
-//! Both functions perform frequent reads and writes, so it's recommended to
-//! use buffered IO implementations such as `std::io::BufReader` and
-//! `std::io::BufWriter` for streaming applications.
+//! Both functions buffer their reads and writes internally, so they perform
+//! the same whether or not the IO implementations provided are buffered, such
+//! as with `std::io::BufReader` and `std::io::BufWriter`.
+//! 
+//! `decode_image_with` accepts `DecodeOptions` to customize decoding, such as
+//! writing decoded pixels in a specific `PixelFormat` like BGRA, compositing
+//! transparent pixels onto a background color when decoding to RGB,
+//! premultiplying alpha, or recovering the pixels of a truncated image with a
+//! `Recovery` option. Similarly, `encode_image_with` accepts `EncodeOptions`,
+//! such as to encode pixels that have premultiplied alpha.
+//! 
+//! Decoding or encoding a large image can take a while, so both option types
+//! accept a `Progress` callback that's called every so many rows, and that
+//! can cancel by returning `ControlFlow::Break`, failing with
+//! `Error::Cancelled`.
+//! 
+//! Images that are already in memory can be decoded faster with
+//! `decode_to_slice` or `decode_to_vec`, which avoid IO entirely, or
+//! customized with `decode_to_slice_with` or `decode_to_vec_with`. To decode
+//! into a buffer with padded rows, such as a GPU upload buffer, use
+//! `decode_to_slice_with` and `DecodeOptions::stride`. Likewise, pixel data in
+//! memory can be encoded with `encode_to_slice` or `encode_to_vec`. For
+//! floating point pipelines, `decode_to_f32_slice` and `decode_to_f32_vec`
+//! decode to normalized `f32` values, optionally converting sRGB images to
+//! linear light with `DecodeOptions::linearize`.
+//! 
+//! Bottom-up pixel data, as used by OpenGL textures and BMP images, can be
+//! decoded and encoded with the `flip_vertically` options. Flipping requires
+//! rows to be accessed out of order, so it's supported in memory by
+//! `decode_to_slice_with`, `decode_to_vec_with`, `encode_to_slice_with`, and
+//! `encode_to_vec_with`, and for IO streams that can seek by
+//! `decode_image_seekable` and `encode_image_seekable`.
+//! 
+//! To decode only part of an image, such as a tile or a horizontal band, use
+//! `decode_region`. To decode a downscaled thumbnail of an image without
+//! decoding it at full size first, use `decode_thumbnail`.
+//! 
+//! To decode each color channel into its own plane, for example for CHW
+//! tensors, use `decode_planar` or `decode_planar_to_slices`. Images with
+//! alpha can be flattened onto a background color with `decode_planar_with`
+//! or `decode_planar_to_slices_with`.
+//! 
+//! To decode an image one row at a time, use a `Decoder`, or to walk its
+//! pixels, use `decode_pixels`. A `DecoderReader` exposes an image's decoded
+//! pixel data as a `std::io::Read` implementation. Images that arrive in
+//! chunks, such as from a non-blocking socket, can be decoded as they arrive
+//! with a `StreamingDecoder`.
+//! 
+//! Like `decode_image_with`, `decode_region_with`, `decode_thumbnail_with`,
+//! `decode_planar_with`, and `decode_pixels_with` accept `DecodeOptions`,
+//! such as `DecodeLimits` to safely decode untrusted images.
+//! 
+//! To inspect an image without decoding it, use `read_header` or `is_qoi`.
+//! To find out how an image is encoded, for example why it compresses badly,
+//! decode it with `decode_image_with_stats`.
+//! 
+//! Errors caused by corrupt or truncated images carry an `ErrorLocation`,
+//! with the byte offset, pixel position, and last op at which decoding
+//! failed.
+//! 
+//! # Features
+//! 
+//! - `std` (default) - adds the IO based APIs, such as `decode_image`,
+//!   `encode_image`, `Decoder`, and `DecoderReader`. Implies `alloc`.
+//! - `alloc` - adds the APIs that allocate, such as `decode_to_vec`,
+//!   `encode_to_vec`, and `StreamingDecoder`.
+//! - `async` - adds `decode_image_async` and `encode_image_async`, which
+//!   decode and encode over the `futures-io` `AsyncRead` and `AsyncWrite`
+//!   traits without blocking. Implies `std`.
+//! 
+//! Without the default features, this crate is `no_std` and still decodes and
+//! encodes images held in memory with `decode_to_slice`,
+//! `decode_to_slice_with`, and `encode_to_slice`, which is useful for
+//! embedded targets. Size the destination buffers with
+//! `ImageMeta::decoded_len` and `ImageMeta::max_encoded_len`.
 //! 
 //! To keep this crate simple, it does not support decoding other image
 //! formats. To encode an image, it will first need to be decoded using another
@@ -49,19 +121,18 @@
 //! 
 //! ```rust
 //! use std::fs::File;
-//! use std::io::{BufReader, BufWriter, sink};
+//! use std::io::sink;
 //! use qoi::{decode_image, Error, ImageMeta};
 //! 
+//! // Reads and writes are buffered internally, so there's no need to wrap
+//! // the file in a `BufReader`.
 //! let image_source = File::open("./tests/testcard_rgba_256x256.qoi")
 //!   .expect("Failed to open image file");
 //! 
-//! let mut reader = BufReader::new(image_source);
-//! 
 //! // For example purposes, write decoded bytes into the void.
 //! let image_destination = sink();
-//! let mut writer = BufWriter::new(image_destination);
 //! 
-//! match decode_image(&mut reader, &mut writer) {
+//! match decode_image(image_source, image_destination) {
 //!   Ok(image_meta) => {
 //!     // Metadata describing the decoded image.
 //!     dbg!(image_meta);
@@ -74,15 +145,86 @@
 //! ```
 //! 
 
-pub use crate::decode::decode_image;
-pub use crate::encode::encode_image;
-pub use crate::error::Error;
-pub use crate::meta::{Colorspace, ImageMeta};
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "async")]
+pub use crate::async_io::{decode_image_async, encode_image_async};
+#[cfg(feature = "std")]
+pub use crate::decode::{
+  decode_image,
+  decode_image_seekable,
+  decode_image_with,
+  decode_image_with_stats,
+  decode_pixels,
+  decode_pixels_with,
+  decode_planar,
+  decode_planar_with,
+  decode_region,
+  decode_region_with,
+  read_header,
+};
+#[cfg(feature = "alloc")]
+pub use crate::decode::{decode_to_f32_vec, decode_to_vec, decode_to_vec_with};
+pub use crate::decode::{
+  decode_planar_to_slices,
+  decode_planar_to_slices_with,
+  decode_to_f32_slice,
+  decode_to_slice,
+  decode_to_slice_with,
+  is_qoi,
+};
+#[cfg(feature = "std")]
+pub use crate::decoder::{Decoder, Pixels};
+#[cfg(feature = "std")]
+pub use crate::encode::{encode_image, encode_image_seekable, encode_image_with};
+#[cfg(feature = "alloc")]
+pub use crate::encode::{encode_to_vec, encode_to_vec_with};
+pub use crate::encode::{encode_to_slice, encode_to_slice_with};
+pub use crate::error::{Error, ErrorLocation};
+pub use crate::format::PixelFormat;
+pub use crate::meta::{Colorspace, ImageMeta, QOI_BYTES_MAGIC, QOI_HEADER_LEN};
+pub use crate::op::OpKind;
+pub use crate::options::{DecodeLimits, DecodeOptions, EncodeOptions, Progress, Recovery};
+pub use crate::pixel::Pixel;
+pub use crate::rect::Rect;
+#[cfg(feature = "std")]
+pub use crate::reader::DecoderReader;
+pub use crate::report::{DecodeReport, EndMarker};
+#[cfg(feature = "std")]
+pub use crate::stats::{DecodeStats, OpStats};
+#[cfg(feature = "std")]
+pub use crate::thumbnail::{decode_thumbnail, decode_thumbnail_with};
+#[cfg(feature = "alloc")]
+pub use crate::streaming::StreamingDecoder;
 
+#[cfg(feature = "async")]
+mod async_io;
+#[cfg(feature = "std")]
+mod buffer;
 mod decode;
+#[cfg(feature = "std")]
+mod decoder;
 mod encode;
 mod error;
+mod format;
 mod meta;
 mod op;
+mod options;
 mod pixel;
+mod progress;
+#[cfg(feature = "std")]
+mod reader;
+mod rect;
+mod report;
+mod sink;
+mod srgb;
 mod state;
+#[cfg(feature = "std")]
+mod stats;
+#[cfg(feature = "alloc")]
+mod streaming;
+#[cfg(feature = "std")]
+mod thumbnail;
@@ -1,17 +1,24 @@
 use crate::error::Error;
 
 pub const QOI_BYTES_END: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+/// The magic bytes every QOI image starts with, "qoif".
 pub const QOI_BYTES_MAGIC: &[u8] = b"qoif";
 pub const QOI_MAX_RUN: u8 = 62;
+/// The length in bytes of a QOI image header.
 pub const QOI_HEADER_LEN: usize = 14;
 
 /// Metadata describing an Image.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct ImageMeta {
   /// The number of color channels the image's pixels contain. For example,
-  /// RGBA pixels have four channels, and RGB have three. Color channels are
-  /// assumed to not be pre-multiplied with the alpha channel
-  /// ("un-premultiplied alpha").
+  /// RGBA pixels have four channels, and RGB have three. Only 3 and 4 are
+  /// valid. Color channels are assumed to not be pre-multiplied with the alpha
+  /// channel ("un-premultiplied alpha"), see `DecodeOptions::premultiply`
+  /// and `EncodeOptions::unpremultiply` to convert from and to premultiplied
+  /// alpha.
+  /// 
+  /// The number of channels is independent of the image's colorspace, an
+  /// image of either colorspace may have three or four channels.
   pub channels: u8,
   /// The image's colorspace, see [Colorspace].
   pub colorspace: Colorspace,
@@ -22,20 +29,57 @@ pub struct ImageMeta {
 }
 
 impl ImageMeta {
-  /// Returns the total number of pixels that make up the image.
-  pub fn num_pixels(&self) -> usize {
-    (self.width * self.height) as usize
+  /// Returns the total number of pixels that make up the image. The product
+  /// of two `u32` dimensions always fits into a `u64`, so this never
+  /// overflows.
+  pub fn num_pixels(&self) -> u64 {
+    self.width as u64 * self.height as u64
+  }
+
+  /// Returns the number of bytes the image's pixel data occupies once
+  /// decoded, with the number of channels described by the image. Returns
+  /// `None` if the number of bytes can't be represented by a `usize` on the
+  /// current platform, in which case the image can't be decoded into memory.
+  pub fn decoded_len(&self) -> Option<usize> {
+    decoded_len(self.num_pixels(), self.channels as usize)
+  }
+
+  /// Returns the largest number of bytes the image can occupy once encoded,
+  /// which is the size of a buffer guaranteed to fit the encoded image.
+  /// Returns `None` if the number of bytes can't be represented by a `usize`
+  /// on the current platform.
+  pub fn max_encoded_len(&self) -> Option<usize> {
+    decoded_len(self.num_pixels(), self.channels as usize + 1)?
+      .checked_add(QOI_HEADER_LEN + QOI_BYTES_END.len())
   }
 }
 
-/// How an image's colors, or pixels, are organized. Supports only sRGB (RGBA)
-/// and Linear (RGB) for now.
+/// The colorspace an image's color channels are encoded in. Either sRGB with
+/// linear alpha, or all channels linear. It doesn't affect how an image is
+/// encoded, nor how many channels it has, and pixels are decoded as is unless
+/// a conversion is requested, such as linearizing with
+/// `decode_to_f32_slice`.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Colorspace {
   Linear = 1,
   Srgb = 0,
 }
 
+// Computes the number of bytes `num_pixels` pixels of `pixel_len` bytes each
+// occupy, if it can be represented by a `usize`.
+pub fn decoded_len(num_pixels: u64, pixel_len: usize) -> Option<usize> {
+  usize::try_from(num_pixels).ok()?.checked_mul(pixel_len)
+}
+
+// Checks that the given number of channels is supported by the QOI format,
+// returning the number of channels if so.
+pub fn validate_channels(channels: u8) -> Result<u8, Error> {
+  match channels {
+    3 | 4 => Ok(channels),
+    _ => Err(Error::InvalidChannels(channels)),
+  }
+}
+
 /// A `TryFrom` implemenation for converting any `u8` into a `Colorspace`.
 /// `1` maps to `Colorspace::Linear`, and `0` maps to `Colorspace::Srgb`. All
 /// other `u8` values are invalid.
@@ -1,6 +1,5 @@
-use std::io;
-
-use crate::error::Error;
+use crate::error::{Error, ErrorLocation};
+use crate::sink::Sink;
 
 // An enumeration of each possible QOI encoding "chunk", or Op.
 pub enum Op {
@@ -55,6 +54,23 @@ pub enum Op {
   Run(u8),
 }
 
+/// The kind of a QOI encoding chunk, or op, without its data.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum OpKind {
+  /// `QOI_OP_DIFF`, a small difference from the previous pixel.
+  Color,
+  /// `QOI_OP_INDEX`, a previously seen pixel.
+  Index,
+  /// `QOI_OP_LUMA`, a difference from the previous pixel based on green.
+  Luma,
+  /// `QOI_OP_RGB`, the red, green, and blue values of a pixel.
+  Rgb,
+  /// `QOI_OP_RGBA`, the red, green, blue, and alpha values of a pixel.
+  Rgba,
+  /// `QOI_OP_RUN`, a run of the previous pixel.
+  Run,
+}
+
 impl Op {
   const MASK_COLOR: u8 = 0x03;
   const MASK_LUMA_1: u8 = 0x3f;
@@ -69,85 +85,102 @@ impl Op {
   const TAG_RGBA: u8 = 0xff;
   const TAG_RUN: u8 = 0xc0;
 
-  // Encodes the `Op` and writes it as bytes into the given writer.
-  pub fn into_bytes<W: io::Write>(self, mut writer: W) -> Result<(), io::Error> {
+  // The maximum number of bytes a single `Op` is encoded with (`Op::Rgba`).
+  #[cfg(feature = "alloc")]
+  pub const MAX_LEN: usize = 5;
+
+  // Returns the kind of the `Op`.
+  pub fn kind(&self) -> OpKind {
+    match self {
+      Op::Color(..) => OpKind::Color,
+      Op::Index(..) => OpKind::Index,
+      Op::Luma(..) => OpKind::Luma,
+      Op::Rgb(..) => OpKind::Rgb,
+      Op::Rgba(..) => OpKind::Rgba,
+      Op::Run(..) => OpKind::Run,
+    }
+  }
+
+  // Encodes the `Op` and writes it as bytes into the given sink.
+  pub fn into_bytes<S: Sink>(self, sink: &mut S) -> Result<(), Error> {
     match self {
       Op::Color(diff_r, diff_g, diff_b) => {
-        writer.write_all(&[Op::TAG_COLOR | (diff_r << 4) | (diff_g << 2) | diff_b])?;
+        sink.write_bytes(&[Op::TAG_COLOR | (diff_r << 4) | (diff_g << 2) | diff_b])
       }
       Op::Index(index) => {
-        writer.write_all(&[Op::TAG_INDEX | index as u8])?;
+        sink.write_bytes(&[Op::TAG_INDEX | index])
       }
       Op::Luma(luma_g, luma_rg, luma_bg) => {
-        writer.write_all(&[Op::TAG_LUMA | luma_g, (luma_rg << 4) | luma_bg])?;
+        sink.write_bytes(&[Op::TAG_LUMA | luma_g, (luma_rg << 4) | luma_bg])
       }
       Op::Rgb(r, g, b) => {
-        writer.write_all(&[Op::TAG_RGB, r, g, b])?;
+        sink.write_bytes(&[Op::TAG_RGB, r, g, b])
       }
       Op::Rgba(r, g, b, a) => {
-        writer.write_all(&[Op::TAG_RGBA, r, g, b, a])?;
+        sink.write_bytes(&[Op::TAG_RGBA, r, g, b, a])
       }
       Op::Run(run_count) => {
-        writer.write_all(&[Op::TAG_RUN | (run_count - 1)])?;
+        sink.write_bytes(&[Op::TAG_RUN | (run_count - 1)])
       }
     }
-
-    Ok(())
   }
 
-  // Attempts to decode an `Op` from the given bytes.
-  pub fn try_from_bytes<I>(bytes: &mut I) -> Result<Self, Error>
-  where
-    I: Iterator<Item = Result<u8, io::Error>>,
-  {
-    let byte = bytes.next().ok_or(Error::UnexpectedEof)??;
+  // Attempts to decode an `Op` from the start of the given bytes. Returns the
+  // `Op` along with the number of bytes it was encoded with. Errors don't
+  // have a location yet, the caller attaches it with `Error::at`.
+  #[inline]
+  pub fn try_from_slice(bytes: &[u8]) -> Result<(Self, usize), Error> {
+    let byte = *bytes.first().ok_or(Error::UnexpectedEof(None))?;
 
     if byte == Op::TAG_RGB {
-      return Ok(Op::Rgb(
-        bytes.next().ok_or(Error::UnexpectedEof)??,
-        bytes.next().ok_or(Error::UnexpectedEof)??,
-        bytes.next().ok_or(Error::UnexpectedEof)??,
-      ));
+      return match bytes.get(1..4) {
+        Some(&[r, g, b]) => Ok((Op::Rgb(r, g, b), 4)),
+        _ => Err(Error::UnexpectedEof(None)),
+      };
     }
 
     if byte == Op::TAG_RGBA {
-      return Ok(Op::Rgba(
-        bytes.next().ok_or(Error::UnexpectedEof)??,
-        bytes.next().ok_or(Error::UnexpectedEof)??,
-        bytes.next().ok_or(Error::UnexpectedEof)??,
-        bytes.next().ok_or(Error::UnexpectedEof)??,
-      ));
+      return match bytes.get(1..5) {
+        Some(&[r, g, b, a]) => Ok((Op::Rgba(r, g, b, a), 5)),
+        _ => Err(Error::UnexpectedEof(None)),
+      };
     }
 
     match byte & Op::MASK_TAG {
       Op::TAG_COLOR => {
-        Ok(Op::Color(
-          byte >> 4 & Op::MASK_COLOR,
-          byte >> 2 & Op::MASK_COLOR,
-          byte & Op::MASK_COLOR,
+        Ok((
+          Op::Color(
+            byte >> 4 & Op::MASK_COLOR,
+            byte >> 2 & Op::MASK_COLOR,
+            byte & Op::MASK_COLOR,
+          ),
+          1,
         ))
       }
       Op::TAG_INDEX => {
         if !(0..=64).contains(&byte) {
-          return Err(Error::InvalidIndex(byte));
+          return Err(Error::InvalidIndex(byte, ErrorLocation::default()));
         }
 
-        Ok(Op::Index(byte))
+        Ok((Op::Index(byte), 1))
       }
       Op::TAG_LUMA => {
-        let next_byte = bytes.next().ok_or(Error::UnexpectedEof)??;
-
-        Ok(Op::Luma(
-          byte & Op::MASK_LUMA_1,
-          next_byte >> 4 & Op::MASK_LUMA_2,
-          next_byte & Op::MASK_LUMA_2,
+        let next_byte = *bytes.get(1).ok_or(Error::UnexpectedEof(None))?;
+
+        Ok((
+          Op::Luma(
+            byte & Op::MASK_LUMA_1,
+            next_byte >> 4 & Op::MASK_LUMA_2,
+            next_byte & Op::MASK_LUMA_2,
+          ),
+          2,
         ))
       }
       Op::TAG_RUN => {
-        Ok(Op::Run(byte & Op::MASK_RUN))
+        Ok((Op::Run(byte & Op::MASK_RUN), 1))
       }
       _ => {
-        Err(Error::UnknownTag(byte))
+        Err(Error::UnknownTag(byte, ErrorLocation::default()))
       },
     }
   }
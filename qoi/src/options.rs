@@ -0,0 +1,325 @@
+use core::fmt;
+use core::ops::{BitOr, ControlFlow};
+
+use crate::error::Error;
+use crate::format::PixelFormat;
+use crate::pixel::Pixel;
+use crate::meta::{decoded_len, ImageMeta};
+
+/// Options for customizing how an image is decoded, see `decode_image_with`.
+/// 
+/// The default options decode an image exactly as described by its header.
+/// Not every function that accepts options supports all of them, options
+/// that aren't supported are never ignored, decoding fails with
+/// `Error::UnsupportedOption` instead. `limits` are always supported.
+#[derive(Clone, Debug, Default)]
+pub struct DecodeOptions<'a> {
+  /// The pixel format decoded pixels are written in, regardless of the number
+  /// of channels the image has. When `None`, pixels are written as RGB or
+  /// RGBA to match the image's header.
+  pub format: Option<PixelFormat>,
+  /// The background color that transparent pixels are composited onto when
+  /// decoding an image with alpha into a `format` without alpha, such as
+  /// `PixelFormat::Rgb`. The background's alpha is ignored, it's always
+  /// opaque. Compositing is gamma-correct, it happens in linear light for
+  /// images with the `Colorspace::Srgb` colorspace.
+  /// 
+  /// Alpha is never dropped silently, so decoding an image with alpha into a
+  /// `format` without alpha fails with `Error::MissingBackground` when this
+  /// is `None` (the default). Images without alpha don't need it.
+  pub background: Option<Pixel>,
+  /// Whether to write the image's rows bottom-up, as expected by OpenGL
+  /// textures and BMP images.
+  /// 
+  /// Rows can only be written out of order into a slice or a seekable
+  /// writer, so this is only supported by `decode_to_slice_with`,
+  /// `decode_to_vec_with`, the `f32` decoding functions, and
+  /// `decode_image_seekable`.
+  pub flip_vertically: bool,
+  /// Limits an image must be within to be decoded, see [DecodeLimits].
+  pub limits: DecodeLimits,
+  /// Whether to convert the color channels of images with the
+  /// `Colorspace::Srgb` colorspace from sRGB to linear light. Alpha, and the
+  /// channels of `Colorspace::Linear` images, are only normalized.
+  /// 
+  /// Only supported when decoding to `f32` values with `decode_to_f32_slice`
+  /// or `decode_to_f32_vec`.
+  pub linearize: bool,
+  /// Whether to premultiply the color channels of decoded pixels by their
+  /// alpha, as expected by most compositors and GPU texture uploads. Values
+  /// are rounded to the nearest integer. Images with 3 channels, and pixels
+  /// composited onto a `background`, are opaque and unaffected.
+  pub premultiply: bool,
+  /// A callback that reports progress as rows are decoded, and can cancel
+  /// decoding, see [Progress].
+  /// 
+  /// Supported by `decode_image_with`, `decode_image_seekable`,
+  /// `decode_to_slice_with`, `decode_to_vec_with`, and the `f32` decoding
+  /// functions. A `Decoder` or `StreamingDecoder` is driven by the caller, which can track progress
+  /// itself, so neither supports it.
+  pub progress: Option<Progress<'a>>,
+  /// How to recover from pixel data that fails to decode, for example
+  /// because the image is truncated, see [Recovery]. When `None` (the
+  /// default), decoding fails instead. Errors reading the image source, such
+  /// as `Error::IoError`, are never recovered from.
+  /// 
+  /// Supported by `decode_image_with`, `decode_image_seekable`,
+  /// `decode_pixels_with`, `decode_to_slice_with`, `decode_to_vec_with`, the
+  /// `f32` decoding functions, and a `Decoder`. A `StreamingDecoder` can't
+  /// tell that an image is truncated until it's finished, so it doesn't
+  /// support it.
+  pub recovery: Option<Recovery>,
+  /// The number of bytes from the start of one row of decoded pixels to the
+  /// start of the next, which must be at least the length of a row. When
+  /// `None` (the default), rows are tightly packed.
+  /// 
+  /// Only supported when decoding into a slice with `decode_to_slice_with`,
+  /// `decode_to_vec_with`, or the `f32` decoding functions, where the padding
+  /// after each row is left untouched, or zeroed when allocated.
+  pub stride: Option<usize>,
+  /// Whether to strictly validate the end of an image. When `true`, the
+  /// image's last pixel must be followed by a complete and valid end marker,
+  /// and nothing else, otherwise decoding fails. Checking for trailing data
+  /// requires reading past the end marker, so the image source is expected to
+  /// end with the image.
+  /// 
+  /// When `false` (the default), a truncated, missing, or invalid end marker
+  /// is accepted and reported in the returned `DecodeReport`. Either way, the
+  /// image source is read in chunks, so a reader may still be read past the
+  /// end marker.
+  pub strict: bool,
+}
+
+impl DecodeOptions<'_> {
+  // Fails with `Error::UnsupportedOption` if an option other than `limits`
+  // and the `supported` ones is set, so that it isn't silently ignored. The
+  // options are destructured, so a new option can't be left unchecked.
+  pub(crate) fn check_supported(&self, supported: SupportedOptions) -> Result<(), Error> {
+    let DecodeOptions {
+      format,
+      background,
+      flip_vertically,
+      limits: _,
+      linearize,
+      premultiply,
+      progress,
+      recovery,
+      stride,
+      strict,
+    } = self;
+
+    supported.check(&[
+      (SupportedOptions::BACKGROUND, "background", background.is_some()),
+      (SupportedOptions::FLIP_VERTICALLY, "flip_vertically", *flip_vertically),
+      (SupportedOptions::FORMAT, "format", format.is_some()),
+      (SupportedOptions::LINEARIZE, "linearize", *linearize),
+      (SupportedOptions::PREMULTIPLY, "premultiply", *premultiply),
+      (SupportedOptions::PROGRESS, "progress", progress.is_some()),
+      (SupportedOptions::RECOVERY, "recovery", recovery.is_some()),
+      (SupportedOptions::STRIDE, "stride", stride.is_some()),
+      (SupportedOptions::STRICT, "strict", *strict),
+    ])
+  }
+
+  // Returns a copy of the options without the progress callback, which can
+  // be stored without borrowing it.
+  #[cfg(feature = "alloc")]
+  pub(crate) fn without_progress(&self) -> DecodeOptions<'static> {
+    DecodeOptions {
+      format: self.format,
+      background: self.background,
+      flip_vertically: self.flip_vertically,
+      limits: self.limits,
+      linearize: self.linearize,
+      premultiply: self.premultiply,
+      progress: None,
+      recovery: self.recovery,
+      stride: self.stride,
+      strict: self.strict,
+    }
+  }
+}
+
+/// How to recover from pixel data that fails to decode, see
+/// `DecodeOptions::recovery`. The error along with the number of pixels that
+/// were decoded before it are reported in the returned `DecodeReport`, and
+/// the end marker isn't read.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Recovery {
+  /// Stop decoding, so only the pixels decoded before the error are written.
+  Truncate,
+  /// Write the given pixel in place of each pixel that couldn't be decoded,
+  /// so the decoded pixel data is always complete.
+  Fill(Pixel),
+}
+
+/// A callback that reports the progress of decoding or encoding an image, see
+/// `DecodeOptions::progress` and `EncodeOptions::progress`.
+/// 
+/// The callback is called with the number of rows that are done and the
+/// total number of rows, every `interval` rows and once the last row is done.
+/// Returning `ControlFlow::Break` cancels decoding or encoding, which then
+/// fails with `Error::Cancelled`.
+/// 
+/// ```rust
+/// use std::cell::Cell;
+/// use std::ops::ControlFlow;
+/// use qoi::{decode_image_with, DecodeOptions, Error, Progress};
+/// 
+/// let image = include_bytes!("../tests/testcard_rgba_256x256.qoi");
+/// let cancelled = Cell::new(true);
+/// 
+/// let callback = |rows_done, rows_total| {
+///   println!("{} of {} rows decoded", rows_done, rows_total);
+/// 
+///   match cancelled.get() {
+///     true => ControlFlow::Break(()),
+///     false => ControlFlow::Continue(()),
+///   }
+/// };
+/// 
+/// let options = DecodeOptions {
+///   progress: Some(Progress { callback: &callback, interval: 64 }),
+///   ..Default::default()
+/// };
+/// 
+/// let result = decode_image_with(image.as_slice(), std::io::sink(), &options);
+/// assert!(matches!(result, Err(Error::Cancelled)));
+/// ```
+#[derive(Clone, Copy)]
+pub struct Progress<'a> {
+  /// The callback, called with the number of rows done and the total number
+  /// of rows.
+  pub callback: &'a dyn Fn(u32, u32) -> ControlFlow<()>,
+  /// The number of rows between calls to the callback. An interval of 0 is
+  /// treated as 1.
+  pub interval: u32,
+}
+
+impl fmt::Debug for Progress<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Progress").field("interval", &self.interval).finish_non_exhaustive()
+  }
+}
+
+/// Upper bounds on the size of an image that may be decoded. An image's
+/// header is checked against the limits before any pixels are decoded, and
+/// `Error::LimitExceeded` is returned if any of them are exceeded.
+/// 
+/// A QOI image of only a few bytes may describe billions of pixels, so
+/// setting limits is strongly recommended when decoding untrusted images. By
+/// default, there are no limits.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct DecodeLimits {
+  /// The maximum width of an image, in pixels.
+  pub max_width: Option<u32>,
+  /// The maximum height of an image, in pixels.
+  pub max_height: Option<u32>,
+  /// The maximum number of pixels an image may contain.
+  pub max_pixels: Option<u64>,
+  /// The maximum number of bytes the decoded pixel data may occupy, in the
+  /// pixel format that's being decoded to. When decoding to `f32` values,
+  /// each value takes up 4 bytes.
+  pub max_bytes: Option<usize>,
+}
+
+impl DecodeLimits {
+  // Checks that the image described by `meta`, decoded with `pixel_len`
+  // bytes per pixel, is within the limits.
+  pub(crate) fn check(&self, meta: &ImageMeta, pixel_len: usize) -> Result<(), Error> {
+    let num_pixels = meta.num_pixels();
+    let exceeds = |limit: Option<u64>, value: u64| matches!(limit, Some(limit) if value > limit);
+
+    if exceeds(self.max_width.map(u64::from), meta.width as u64)
+      || exceeds(self.max_height.map(u64::from), meta.height as u64)
+      || exceeds(self.max_pixels, num_pixels)
+    {
+      return Err(Error::LimitExceeded);
+    }
+
+    if let Some(max_bytes) = self.max_bytes {
+      match decoded_len(num_pixels, pixel_len) {
+        Some(len) if len <= max_bytes => {}
+        _ => return Err(Error::LimitExceeded),
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Options for customizing how an image is encoded, see
+/// `encode_image_with`.
+/// 
+/// The default options encode an image's pixel data exactly as provided.
+#[derive(Clone, Debug, Default)]
+pub struct EncodeOptions<'a> {
+  /// Whether the provided pixel data's rows are bottom-up, such as the output
+  /// of `glReadPixels`, in which case they're encoded in reverse order.
+  /// 
+  /// Rows can only be read out of order from a slice or a seekable reader,
+  /// so this is only supported by `encode_to_slice_with`,
+  /// `encode_to_vec_with`, and `encode_image_seekable`. `encode_image_with`
+  /// fails with `Error::UnsupportedOption` instead.
+  pub flip_vertically: bool,
+  /// A callback that reports progress as rows are encoded, and can cancel
+  /// encoding, see [Progress].
+  pub progress: Option<Progress<'a>>,
+  /// Whether the provided pixels have premultiplied alpha, which is then
+  /// divided out of their color channels before encoding, since QOI images
+  /// store un-premultiplied alpha. Values are rounded to the nearest integer.
+  /// Only affects images with 4 channels.
+  pub unpremultiply: bool,
+}
+
+impl EncodeOptions<'_> {
+  // Fails with `Error::UnsupportedOption` if an option other than the
+  // `supported` ones is set, so that it isn't silently ignored.
+  #[cfg(feature = "std")]
+  pub(crate) fn check_supported(&self, supported: SupportedOptions) -> Result<(), Error> {
+    let EncodeOptions { flip_vertically, progress, unpremultiply } = self;
+
+    supported.check(&[
+      (SupportedOptions::FLIP_VERTICALLY, "flip_vertically", *flip_vertically),
+      (SupportedOptions::PROGRESS, "progress", progress.is_some()),
+      (SupportedOptions::UNPREMULTIPLY, "unpremultiply", *unpremultiply),
+    ])
+  }
+}
+
+// A set of decode or encode options that a function supports, combined with
+// `|`. Each option is a constant, so a misspelled or renamed option fails to
+// compile.
+#[derive(Clone, Copy)]
+pub(crate) struct SupportedOptions(u16);
+
+impl SupportedOptions {
+  pub const BACKGROUND: Self = Self(1);
+  pub const FLIP_VERTICALLY: Self = Self(1 << 1);
+  pub const FORMAT: Self = Self(1 << 2);
+  pub const LINEARIZE: Self = Self(1 << 3);
+  pub const PREMULTIPLY: Self = Self(1 << 4);
+  pub const PROGRESS: Self = Self(1 << 5);
+  pub const RECOVERY: Self = Self(1 << 6);
+  pub const STRIDE: Self = Self(1 << 7);
+  pub const STRICT: Self = Self(1 << 8);
+  #[cfg(feature = "std")]
+  pub const UNPREMULTIPLY: Self = Self(1 << 9);
+
+  // Fails with `Error::UnsupportedOption` naming the first of the given
+  // options that's set, but isn't in this set.
+  fn check(self, options: &[(Self, &'static str, bool)]) -> Result<(), Error> {
+    match options.iter().find(|(option, _, is_set)| *is_set && self.0 & option.0 == 0) {
+      Some((_, name, _)) => Err(Error::UnsupportedOption(name)),
+      None => Ok(()),
+    }
+  }
+}
+
+impl BitOr for SupportedOptions {
+  type Output = Self;
+
+  fn bitor(self, other: Self) -> Self {
+    Self(self.0 | other.0)
+  }
+}
@@ -1,3 +1,6 @@
+use crate::meta::Colorspace;
+use crate::srgb::{linear_to_srgb, srgb_to_linear};
+
 // An enumeration of the possible pixel "diffs".
 pub enum PixelDiff {
   // A color, or `QOI_OP_DIFF` diff with bias applied.
@@ -6,15 +9,17 @@ pub enum PixelDiff {
   Luma(u8, u8, u8),
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// A single decoded pixel, with un-premultiplied alpha. Pixels of images
+/// with three channels are always fully opaque.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct Pixel {
-  // Red channel value.
+  /// Red channel value.
   pub r: u8,
-  // Green channel value.
+  /// Green channel value.
   pub g: u8,
-  // Blue channel value.
+  /// Blue channel value.
   pub b: u8,
-  // Alpha channel value.
+  /// Alpha channel value.
   pub a: u8,
 }
 
@@ -28,7 +33,7 @@ impl Pixel {
   // Attempts to produce a `PixelDiff` against the provided `other` pixel.
   // Returns `Some(PixelDiff)` when there is a diff within range, otherwise
   // `None` is returned.
-  pub fn diff(&self, other: &Pixel) -> Option<PixelDiff> {
+  pub(crate) fn diff(&self, other: &Pixel) -> Option<PixelDiff> {
     if self.a != other.a {
       return None;
     }
@@ -64,7 +69,7 @@ impl Pixel {
   }
 
   // Recreates a `Pixel` from the provided `diff` and `diff_pixel`.
-  pub fn from_diff(diff: PixelDiff, diff_pixel: &Pixel) -> Self {
+  pub(crate) fn from_diff(diff: PixelDiff, diff_pixel: &Pixel) -> Self {
     match diff {
       PixelDiff::Color(diff_r, diff_g, diff_b) => Self {
         r: diff_pixel.r.wrapping_add(diff_r.wrapping_sub(2)),
@@ -87,9 +92,67 @@ impl Pixel {
     }
   }
 
+  // Composites the pixel over the given opaque `background`, producing an
+  // opaque pixel. Blending happens in linear light, so for sRGB images the
+  // color channels are linearized first.
+  pub(crate) fn composite(&self, background: Pixel, colorspace: Colorspace) -> Self {
+    let a = self.a as u32;
+
+    let blend = |c: u8, bg: u8| match colorspace {
+      Colorspace::Linear => ((c as u32 * a + bg as u32 * (255 - a) + 127) / 255) as u8,
+      Colorspace::Srgb => {
+        let alpha = a as f32 / 255.0;
+        linear_to_srgb(srgb_to_linear(c) * alpha + srgb_to_linear(bg) * (1.0 - alpha))
+      }
+    };
+
+    match self.a {
+      255 => *self,
+      0 => Self { a: 255, ..background },
+      _ => Self {
+        r: blend(self.r, background.r),
+        g: blend(self.g, background.g),
+        b: blend(self.b, background.b),
+        a: 255,
+      },
+    }
+  }
+
+  // Multiplies the color channels by alpha, rounding to the nearest value.
+  pub(crate) fn premultiply(&self) -> Self {
+    let a = self.a as u32;
+    let multiply = |c: u8| ((c as u32 * a + 127) / 255) as u8;
+
+    Self {
+      r: multiply(self.r),
+      g: multiply(self.g),
+      b: multiply(self.b),
+      a: self.a,
+    }
+  }
+
+  // Divides the premultiplied color channels by alpha, rounding to the
+  // nearest value. Color channels greater than alpha are invalid, and are
+  // clamped. Fully transparent pixels have no color, and become black.
+  pub(crate) fn unpremultiply(&self) -> Self {
+    let a = self.a as u32;
+    let divide = |c: u8| ((c as u32 * 510 + a) / (a * 2)).min(255) as u8;
+
+    match self.a {
+      255 => *self,
+      0 => Self { r: 0, g: 0, b: 0, a: 0 },
+      _ => Self {
+        r: divide(self.r),
+        g: divide(self.g),
+        b: divide(self.b),
+        a: self.a,
+      },
+    }
+  }
+
   // QOI color hash function, not implemented via the `Hash` trait to keep
   // things simple.
-  pub fn qoi_hash(&self) -> usize {
+  pub(crate) fn qoi_hash(&self) -> usize {
     let r = self.r as usize;
     let g = self.g as usize;
     let b = self.b as usize;
@@ -0,0 +1,81 @@
+use core::ops::ControlFlow;
+
+use crate::error::Error;
+use crate::meta::ImageMeta;
+use crate::options::Progress;
+
+// Tracks the number of pixels decoded or encoded so far, and reports the
+// rows that are done to the progress callback (if any) every interval rows.
+// Progress is tracked in pixels, so checking whether a report is due is a
+// single comparison.
+pub struct ProgressTracker<'a> {
+  progress: Option<Progress<'a>>,
+  height: u32,
+  num_pixels: u64,
+  width: u64,
+  // The number of pixels done at which progress is reported next.
+  next_report: u64,
+}
+
+impl<'a> ProgressTracker<'a> {
+  pub fn new(progress: Option<Progress<'a>>, meta: &ImageMeta) -> Self {
+    let mut tracker = Self {
+      progress,
+      height: meta.height,
+      num_pixels: meta.num_pixels(),
+      width: meta.width as u64,
+      next_report: u64::MAX,
+    };
+
+    // Images without pixels have no progress to report.
+    if progress.is_some() && tracker.num_pixels > 0 {
+      tracker.next_report = tracker.next_report_after(0);
+    }
+
+    tracker
+  }
+
+  // Updates the number of pixels done, reporting progress if it's due.
+  // Returns `Error::Cancelled` if the callback cancels.
+  #[inline]
+  pub fn update(&mut self, pixels_done: u64) -> Result<(), Error> {
+    if pixels_done < self.next_report {
+      return Ok(());
+    }
+
+    self.report(pixels_done)
+  }
+
+  #[cold]
+  fn report(&mut self, pixels_done: u64) -> Result<(), Error> {
+    let progress = match self.progress {
+      Some(progress) => progress,
+      None => return Ok(()),
+    };
+
+    let rows_done = (pixels_done / self.width) as u32;
+    self.next_report = self.next_report_after(rows_done);
+
+    match (progress.callback)(rows_done, self.height) {
+      ControlFlow::Continue(()) => Ok(()),
+      ControlFlow::Break(()) => Err(Error::Cancelled),
+    }
+  }
+
+  // Returns the number of pixels done at which progress is reported next,
+  // after the given number of rows are done.
+  fn next_report_after(&self, rows_done: u32) -> u64 {
+    let interval = match self.progress {
+      Some(progress) => progress.interval.max(1) as u64,
+      None => return u64::MAX,
+    };
+
+    if rows_done == self.height {
+      return u64::MAX;
+    }
+
+    let rows = (rows_done as u64 / interval + 1) * interval;
+
+    (rows * self.width).min(self.num_pixels)
+  }
+}
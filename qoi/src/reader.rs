@@ -0,0 +1,111 @@
+use std::io;
+
+use crate::decoder::Decoder;
+use crate::error::Error;
+use crate::meta::ImageMeta;
+use crate::options::DecodeOptions;
+use crate::report::DecodeReport;
+
+/// An adapter that implements `std::io::Read` over the decoded pixel data of
+/// a QOI encoded image. Pixels are decoded on demand as bytes are read, so
+/// a QOI image can be passed to any consumer of bytes without decoding it
+/// into an intermediate buffer first.
+/// 
+/// ```rust
+/// use std::io;
+/// use qoi::DecoderReader;
+/// 
+/// let image = include_bytes!("../tests/testcard_rgba_256x256.qoi");
+/// let mut reader = DecoderReader::new(image.as_slice()).expect("Failed to decode header");
+/// 
+/// let copied = io::copy(&mut reader, &mut io::sink()).expect("Failed to decode image");
+/// assert_eq!(copied, 256 * 256 * 4);
+/// ```
+/// 
+/// Decode errors are returned as `std::io::Error`s of kind `InvalidData` or
+/// `UnexpectedEof`, wrapping the original `Error`.
+pub struct DecoderReader<R> {
+  decoder: Decoder<R>,
+  // The most recently decoded pixel, in the decoder's pixel format.
+  pixel_buf: [u8; 4],
+  // Number of bytes in `pixel_buf` that make up the pixel.
+  pixel_len: usize,
+  // Position of the next byte in `pixel_buf` that hasn't been read yet.
+  pixel_pos: usize,
+}
+
+impl<R: io::Read> DecoderReader<R> {
+  /// Creates a new reader that decodes an image as described by its header,
+  /// decoding the header from the given `reader`.
+  pub fn new(reader: R) -> Result<Self, Error> {
+    Decoder::new(reader).map(Self::from)
+  }
+
+  /// Creates a new reader customized by the given `options`, see
+  /// `decode_image_with`.
+  pub fn with_options(reader: R, options: &DecodeOptions) -> Result<Self, Error> {
+    Decoder::with_options(reader, options).map(Self::from)
+  }
+
+  /// Returns the image's `ImageMeta` data, as found in its header.
+  pub fn meta(&self) -> &ImageMeta {
+    self.decoder.meta()
+  }
+
+  /// Completes decoding, see `Decoder::finish`. Any pixels that haven't been
+  /// read yet are discarded.
+  pub fn finish(self) -> Result<DecodeReport, Error> {
+    self.decoder.finish()
+  }
+}
+
+impl<R: io::Read> From<Decoder<R>> for DecoderReader<R> {
+  fn from(decoder: Decoder<R>) -> Self {
+    Self {
+      decoder,
+      pixel_buf: [0; 4],
+      pixel_len: 0,
+      pixel_pos: 0,
+    }
+  }
+}
+
+impl<R: io::Read> io::Read for DecoderReader<R> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let output = *self.decoder.output();
+    let mut written = 0;
+
+    while written < buf.len() {
+      if self.pixel_pos < self.pixel_len {
+        let pending = &self.pixel_buf[self.pixel_pos..self.pixel_len];
+        let len = pending.len().min(buf.len() - written);
+
+        buf[written..written + len].copy_from_slice(&pending[..len]);
+        self.pixel_pos += len;
+        written += len;
+        continue;
+      }
+
+      match self.decoder.next_pixel() {
+        Ok(Some(pixel)) => {
+          output.write_pixel(pixel, &mut self.pixel_buf);
+          self.pixel_len = output.format.bytes_per_pixel();
+          self.pixel_pos = 0;
+        }
+        Ok(None) => {
+          break;
+        }
+        // A failed pixel doesn't advance the decoder, so return the bytes
+        // read so far and fail on the next read instead.
+        Err(_) if written > 0 => {
+          break;
+        }
+        Err(e) => {
+          return Err(e.into());
+        }
+      }
+    }
+
+    Ok(written)
+  }
+}
@@ -0,0 +1,23 @@
+use crate::meta::ImageMeta;
+
+/// A rectangular region of an image, in pixels, see `decode_region`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Rect {
+  /// The x position of the rectangle's left edge.
+  pub x: u32,
+  /// The y position of the rectangle's top edge.
+  pub y: u32,
+  /// The width of the rectangle.
+  pub width: u32,
+  /// The height of the rectangle.
+  pub height: u32,
+}
+
+impl Rect {
+  /// Returns `true` if the rectangle lies entirely within the image
+  /// described by `meta`.
+  pub fn is_within(&self, meta: &ImageMeta) -> bool {
+    self.x as u64 + self.width as u64 <= meta.width as u64
+      && self.y as u64 + self.height as u64 <= meta.height as u64
+  }
+}
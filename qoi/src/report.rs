@@ -0,0 +1,69 @@
+use crate::error::{Error, ErrorLocation};
+use crate::meta::ImageMeta;
+use crate::meta::QOI_BYTES_END;
+
+/// A report describing a decoded image, returned by `decode_image_with`.
+#[derive(Debug, PartialEq)]
+pub struct DecodeReport {
+  /// The image's metadata, as found in its header.
+  pub meta: ImageMeta,
+  /// The state of the end marker that followed the image's last pixel.
+  pub end_marker: EndMarker,
+  /// The number of pixels decoded from the image's pixel data. Less than
+  /// `meta.num_pixels()` only when decoding recovered from an error.
+  pub pixels_decoded: u64,
+  /// The error decoding recovered from, see `DecodeOptions::recovery`. The
+  /// end marker isn't read after an error, and is reported as truncated.
+  pub error: Option<Error>,
+  /// The number of bytes that follow the end marker, when decoding leniently
+  /// from a slice or with a `StreamingDecoder`. It's `None` when decoding
+  /// from a reader, which isn't read past the end marker just to count them,
+  /// or after recovering from an error. Strict decoding fails with
+  /// `Error::TrailingData` instead.
+  pub trailing_bytes: Option<usize>,
+  /// Whether any data was found after the end marker. When decoding from a
+  /// reader, only data that was already read along with the end marker is
+  /// found, since reading any further could block.
+  pub has_trailing_data: bool,
+}
+
+/// The state of the 8 byte end marker that's expected to follow an image's
+/// last pixel. Anything but `EndMarker::Valid` is only ever reported when
+/// decoding leniently, strict decoding fails instead.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EndMarker {
+  /// The end marker is complete and valid.
+  Valid,
+  /// The image source ended before the end marker was complete. Contains the
+  /// number of end marker bytes that were found, 0 if it's missing entirely.
+  Truncated(usize),
+  /// The bytes following the image's last pixel aren't an end marker.
+  Invalid,
+}
+
+impl EndMarker {
+  // Checks the end marker at the start of the given bytes, which are expected
+  // to directly follow the image's last pixel.
+  pub(crate) fn check(bytes: &[u8]) -> Self {
+    let len = bytes.len().min(QOI_BYTES_END.len());
+
+    if bytes[..len] != QOI_BYTES_END[..len] {
+      return EndMarker::Invalid;
+    }
+
+    if len < QOI_BYTES_END.len() {
+      return EndMarker::Truncated(len);
+    }
+
+    EndMarker::Valid
+  }
+
+  // Fails unless the end marker is valid, as required by strict decoding.
+  pub(crate) fn require_valid(self) -> Result<Self, Error> {
+    match self {
+      EndMarker::Valid => Ok(self),
+      EndMarker::Truncated(_) => Err(Error::UnexpectedEof(None)),
+      EndMarker::Invalid => Err(Error::InvalidEndMarker(ErrorLocation::default())),
+    }
+  }
+}
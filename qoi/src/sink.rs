@@ -0,0 +1,65 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io;
+
+#[cfg(feature = "std")]
+use crate::buffer::WriteBuffer;
+use crate::error::Error;
+
+// A destination for encoded bytes. Abstracts over IO writers, growable
+// buffers, and fixed size slices, so the encoder doesn't depend on `std`.
+pub trait Sink {
+  // Writes all of the given bytes to the sink.
+  fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Error>;
+}
+
+#[cfg(feature = "alloc")]
+impl Sink for Vec<u8> {
+  fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+    self.extend_from_slice(bytes);
+    Ok(())
+  }
+}
+
+// A sink that writes into a fixed size slice. Running out of space fails with
+// `Error::BufferTooSmall`, reporting the given `required` number of bytes.
+pub struct SliceSink<'a> {
+  buf: &'a mut [u8],
+  pos: usize,
+  required: usize,
+}
+
+impl<'a> SliceSink<'a> {
+  pub fn new(buf: &'a mut [u8], required: usize) -> Self {
+    Self { buf, pos: 0, required }
+  }
+
+  // Returns the number of bytes written so far.
+  pub fn len(&self) -> usize {
+    self.pos
+  }
+}
+
+impl Sink for SliceSink<'_> {
+  fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+    let end = self.pos + bytes.len();
+    let dest = self.buf.get_mut(self.pos..end).ok_or(Error::BufferTooSmall(self.required))?;
+
+    dest.copy_from_slice(bytes);
+    self.pos = end;
+
+    Ok(())
+  }
+}
+
+// Writes to an `io::Write` implementation through a buffer, since each `Op`
+// is written separately.
+#[cfg(feature = "std")]
+impl<W: io::Write> Sink for WriteBuffer<W> {
+  #[inline]
+  fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+    self.write_all(bytes)?;
+    Ok(())
+  }
+}
@@ -1,3 +1,4 @@
+use crate::op::OpKind;
 use crate::pixel::Pixel;
 
 // A collection of stateful properties and methods maintained during decoding
@@ -5,6 +6,8 @@ use crate::pixel::Pixel;
 pub struct State {
   // A cache of previously seen pixels, indexed by their hash value % 64.
   pub cache: [Pixel; 64],
+  // The kind of the last decoded `Op` (if any), used to locate errors.
+  pub last_op: Option<OpKind>,
   // The previously decoded/encoded pixel.
   pub prev_pixel: Pixel,
   // Length of the current run (Op::Run) (if any).
@@ -15,6 +18,7 @@ impl State {
   pub fn new() -> Self {
     Self {
       cache: [Pixel { r: 0, g: 0, b: 0, a: 0 }; 64],
+      last_op: None,
       prev_pixel: Pixel::default(),
       run_count: 0,
     }
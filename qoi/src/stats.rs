@@ -0,0 +1,123 @@
+use crate::op::OpKind;
+use crate::state::State;
+
+/// Statistics about how an image is encoded, collected while decoding it
+/// with `decode_image_with_stats`. Useful to find out why an image
+/// compresses well or badly.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DecodeStats {
+  /// Ops of the kind `OpKind::Color`.
+  pub color: OpStats,
+  /// Ops of the kind `OpKind::Index`.
+  pub index: OpStats,
+  /// Ops of the kind `OpKind::Luma`.
+  pub luma: OpStats,
+  /// Ops of the kind `OpKind::Rgb`.
+  pub rgb: OpStats,
+  /// Ops of the kind `OpKind::Rgba`.
+  pub rgba: OpStats,
+  /// Ops of the kind `OpKind::Run`.
+  pub run: OpStats,
+  /// The number of runs of each length, where `run_lengths[i]` counts the
+  /// runs of `i + 1` pixels. A single run is at most 62 pixels long, longer
+  /// runs are split into multiple runs.
+  pub run_lengths: [u64; 62],
+}
+
+/// The number of ops of a single kind, and the number of bytes they take up
+/// in the encoded image, see `DecodeStats`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct OpStats {
+  /// The number of ops.
+  pub count: u64,
+  /// The number of bytes taken up by the ops.
+  pub bytes: u64,
+}
+
+impl DecodeStats {
+  /// Returns the stats of the given kind of op.
+  pub fn op(&self, kind: OpKind) -> &OpStats {
+    match kind {
+      OpKind::Color => &self.color,
+      OpKind::Index => &self.index,
+      OpKind::Luma => &self.luma,
+      OpKind::Rgb => &self.rgb,
+      OpKind::Rgba => &self.rgba,
+      OpKind::Run => &self.run,
+    }
+  }
+
+  /// Returns the total stats of all ops.
+  pub fn total(&self) -> OpStats {
+    [self.color, self.index, self.luma, self.rgb, self.rgba, self.run]
+      .iter()
+      .fold(OpStats::default(), |total, op| OpStats {
+        count: total.count + op.count,
+        bytes: total.bytes + op.bytes,
+      })
+  }
+
+  /// Returns the ratio of pixels found in the index of previously seen
+  /// pixels, out of all pixels that aren't part of a run, in [0, 1]. This is
+  /// the share of ops that are `OpKind::Index` ops, not counting runs. Images
+  /// without any such pixels have a ratio of 0.
+  pub fn index_hit_ratio(&self) -> f64 {
+    let ops = self.total().count - self.run.count;
+
+    match ops {
+      0 => 0.0,
+      ops => self.index.count as f64 / ops as f64,
+    }
+  }
+
+  fn op_mut(&mut self, kind: OpKind) -> &mut OpStats {
+    match kind {
+      OpKind::Color => &mut self.color,
+      OpKind::Index => &mut self.index,
+      OpKind::Luma => &mut self.luma,
+      OpKind::Rgb => &mut self.rgb,
+      OpKind::Rgba => &mut self.rgba,
+      OpKind::Run => &mut self.run,
+    }
+  }
+}
+
+impl Default for DecodeStats {
+  fn default() -> Self {
+    Self {
+      color: OpStats::default(),
+      index: OpStats::default(),
+      luma: OpStats::default(),
+      rgb: OpStats::default(),
+      rgba: OpStats::default(),
+      run: OpStats::default(),
+      run_lengths: [0; 62],
+    }
+  }
+}
+
+// Observes each `Op` as it's decoded. Implemented by `()` to observe nothing,
+// which compiles away entirely, so decoding without stats costs nothing.
+pub(crate) trait Observer {
+  // Called after an op is decoded, with its kind, its length in bytes, and
+  // the decoding state the op was applied to.
+  fn observe_op(&mut self, kind: OpKind, len: usize, state: &State);
+}
+
+impl Observer for () {
+  #[inline(always)]
+  fn observe_op(&mut self, _kind: OpKind, _len: usize, _state: &State) {}
+}
+
+impl Observer for DecodeStats {
+  fn observe_op(&mut self, kind: OpKind, len: usize, state: &State) {
+    let op = self.op_mut(kind);
+    op.count += 1;
+    op.bytes += len as u64;
+
+    // The run's first pixel has been decoded, the rest remain.
+    if kind == OpKind::Run {
+      self.run_lengths[state.run_count as usize] += 1;
+    }
+  }
+}
@@ -0,0 +1,273 @@
+use alloc::vec::Vec;
+
+use crate::decode::{decode_header_from_slice, decode_pixel};
+use crate::error::{Error, ErrorLocation};
+use crate::format::Output;
+use crate::meta::{ImageMeta, QOI_BYTES_END, QOI_HEADER_LEN};
+use crate::op::Op;
+use crate::options::{DecodeOptions, SupportedOptions};
+use crate::report::{DecodeReport, EndMarker};
+use crate::state::State;
+
+/// A push-based QOI decoder for images that arrive in chunks of arbitrary
+/// size, such as from a non-blocking socket.
+/// 
+/// Chunks of the encoded image are passed to `feed`, which decodes as many
+/// pixels as possible and returns their bytes. An `Op` that's split across
+/// chunks is kept until the next chunk completes it. Once the whole image
+/// has been fed, `finish` checks that the image is complete and returns a
+/// `DecodeReport`.
+/// 
+/// ```rust
+/// use qoi::StreamingDecoder;
+/// 
+/// let image = include_bytes!("../tests/testcard_rgba_256x256.qoi");
+/// let mut decoder = StreamingDecoder::new();
+/// let mut pixels = Vec::new();
+/// 
+/// for chunk in image.chunks(1000) {
+///   pixels.extend_from_slice(decoder.feed(chunk).expect("Failed to decode chunk"));
+/// }
+/// 
+/// let report = decoder.finish().expect("Failed to decode image");
+/// assert_eq!(pixels.len(), report.meta.decoded_len().unwrap());
+/// ```
+/// 
+/// Once an error has been returned, the decoder is in an unspecified state
+/// and shouldn't be used any further.
+pub struct StreamingDecoder {
+  // Bytes of an incomplete header or `Op`, carried over to the next chunk.
+  carry: Vec<u8>,
+  // Bytes of the end marker received so far.
+  end_marker: Vec<u8>,
+  // The output format to write pixels in, resolved once the header is known.
+  format: Option<Output>,
+  meta: Option<ImageMeta>,
+  // The byte offset of the next `Op` from the start of the encoded image.
+  offset: u64,
+  options: DecodeOptions<'static>,
+  // Decoded pixel bytes of the most recent chunk.
+  output: Vec<u8>,
+  pixels_remaining: u64,
+  state: State,
+  // The number of bytes received after the end marker.
+  trailing_bytes: usize,
+}
+
+impl Default for StreamingDecoder {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl StreamingDecoder {
+  /// Creates a new decoder that decodes an image as described by its header.
+  pub fn new() -> Self {
+    Self::from_options(DecodeOptions::default())
+  }
+
+  /// Creates a new decoder customized by the given `options`, see
+  /// `decode_image_with`.
+  /// 
+  /// Only the `background`, `format`, `premultiply`, and `strict` options
+  /// are supported, along with `limits`, which are checked once the header
+  /// has been fed. `Error::UnsupportedOption` is returned for any other
+  /// option.
+  pub fn with_options(options: &DecodeOptions) -> Result<Self, Error> {
+    options.check_supported(
+      SupportedOptions::BACKGROUND
+        | SupportedOptions::FORMAT
+        | SupportedOptions::PREMULTIPLY
+        | SupportedOptions::STRICT
+    )?;
+
+    Ok(Self::from_options(options.without_progress()))
+  }
+
+  fn from_options(options: DecodeOptions<'static>) -> Self {
+    Self {
+      carry: Vec::with_capacity(QOI_HEADER_LEN),
+      end_marker: Vec::with_capacity(QOI_BYTES_END.len()),
+      format: None,
+      meta: None,
+      offset: 0,
+      options,
+      output: Vec::new(),
+      pixels_remaining: 0,
+      state: State::new(),
+      trailing_bytes: 0,
+    }
+  }
+
+  /// Returns the image's `ImageMeta` data, once enough of the image has been
+  /// fed to decode its header.
+  pub fn meta(&self) -> Option<&ImageMeta> {
+    self.meta.as_ref()
+  }
+
+  /// Returns `true` once all of the image's pixels and its complete end
+  /// marker have been fed, at which point no more input is expected.
+  pub fn is_complete(&self) -> bool {
+    self.meta.is_some()
+      && self.pixels_remaining == 0
+      && self.end_marker.len() == QOI_BYTES_END.len()
+  }
+
+  /// Decodes the given chunk of the encoded image, returning the bytes of
+  /// all pixels that could be decoded so far. The returned bytes are only
+  /// valid until the next call to `feed`.
+  pub fn feed(&mut self, mut chunk: &[u8]) -> Result<&[u8], Error> {
+    self.output.clear();
+
+    if self.meta.is_none() {
+      chunk = self.feed_header(chunk)?;
+    }
+
+    if self.meta.is_some() {
+      chunk = self.feed_pixels(chunk)?;
+    }
+
+    if !chunk.is_empty() {
+      self.feed_end_marker(chunk)?;
+    }
+
+    Ok(&self.output)
+  }
+
+  /// Completes decoding once the whole image has been fed, checking that no
+  /// pixels are missing and that the end marker is valid when decoding
+  /// strictly. Returns the decoded image's `DecodeReport` upon success.
+  pub fn finish(self) -> Result<DecodeReport, Error> {
+    let meta = match self.meta {
+      Some(meta) if self.pixels_remaining == 0 => meta,
+      _ => return Err(Error::UnexpectedEof(Some(self.location(0)))),
+    };
+
+    let mut end_marker = EndMarker::check(&self.end_marker);
+
+    if self.options.strict {
+      end_marker = end_marker.require_valid().map_err(|e| e.at(self.location(0)))?;
+    }
+
+    Ok(DecodeReport {
+      meta,
+      end_marker,
+      pixels_decoded: meta.num_pixels(),
+      error: None,
+      trailing_bytes: Some(self.trailing_bytes),
+      has_trailing_data: self.trailing_bytes > 0,
+    })
+  }
+
+  // Buffers header bytes from the start of the chunk until the header is
+  // complete, then decodes it. Returns the rest of the chunk.
+  fn feed_header<'a>(&mut self, chunk: &'a [u8]) -> Result<&'a [u8], Error> {
+    let len = chunk.len().min(QOI_HEADER_LEN - self.carry.len());
+    self.carry.extend_from_slice(&chunk[..len]);
+
+    if self.carry.len() == QOI_HEADER_LEN {
+      let meta = decode_header_from_slice(&self.carry)?;
+      let format = Output::new(&meta, &self.options)?;
+
+      self.options.limits.check(&meta, format.format.bytes_per_pixel())?;
+      self.carry.clear();
+      self.format = Some(format);
+      self.offset = QOI_HEADER_LEN as u64;
+      self.pixels_remaining = meta.num_pixels();
+      self.meta = Some(meta);
+    }
+
+    Ok(&chunk[len..])
+  }
+
+  // Decodes as many pixels as possible from the chunk, carrying over an
+  // incomplete `Op` at its end. Returns the rest of the chunk once all
+  // pixels have been decoded, otherwise an empty slice.
+  fn feed_pixels<'a>(&mut self, mut chunk: &'a [u8]) -> Result<&'a [u8], Error> {
+    let format = match self.format {
+      Some(format) => format,
+      None => return Ok(chunk),
+    };
+
+    let pixel_len = format.format.bytes_per_pixel();
+    let mut pixel_buf = [0; 4];
+
+    // Complete an `Op` that was split across chunks first.
+    if !self.carry.is_empty() && self.pixels_remaining > 0 {
+      let carried = self.carry.len();
+      let len = chunk.len().min(Op::MAX_LEN - carried);
+      self.carry.extend_from_slice(&chunk[..len]);
+
+      let mut pos = 0;
+
+      match decode_pixel(&mut self.state, &self.carry, &mut pos) {
+        Ok(pixel) => {
+          format.write_pixel(pixel, &mut pixel_buf);
+          self.output.extend_from_slice(&pixel_buf[..pixel_len]);
+          self.pixels_remaining -= 1;
+          self.offset += pos as u64;
+          self.carry.clear();
+          chunk = &chunk[pos - carried..];
+        }
+        Err(Error::UnexpectedEof(_)) => {
+          return Ok(&[]);
+        }
+        Err(e) => {
+          return Err(e.at(self.location(0)));
+        }
+      }
+    }
+
+    let mut pos = 0;
+
+    while self.pixels_remaining > 0 {
+      let pixel = match decode_pixel(&mut self.state, chunk, &mut pos) {
+        Ok(pixel) => pixel,
+        Err(Error::UnexpectedEof(_)) => {
+          self.carry.extend_from_slice(&chunk[pos..]);
+          self.offset += pos as u64;
+          return Ok(&[]);
+        }
+        Err(e) => {
+          return Err(e.at(self.location(pos)));
+        }
+      };
+
+      format.write_pixel(pixel, &mut pixel_buf);
+      self.output.extend_from_slice(&pixel_buf[..pixel_len]);
+      self.pixels_remaining -= 1;
+    }
+
+    self.offset += pos as u64;
+
+    Ok(&chunk[pos..])
+  }
+
+  // Returns the location of the `Op` that starts `pos` bytes after the
+  // current offset, or of the header or end marker.
+  fn location(&self, pos: usize) -> ErrorLocation {
+    match &self.meta {
+      Some(meta) => {
+        let pixel_index = meta.num_pixels() - self.pixels_remaining;
+        ErrorLocation::new(meta, self.offset + pos as u64, pixel_index, self.state.last_op)
+      }
+      None => ErrorLocation::header(),
+    }
+  }
+
+  // Buffers the end marker that follows the image's last pixel. Any data
+  // after the end marker is an error when decoding strictly, and counted
+  // otherwise.
+  fn feed_end_marker(&mut self, chunk: &[u8]) -> Result<(), Error> {
+    let len = chunk.len().min(QOI_BYTES_END.len() - self.end_marker.len());
+    self.end_marker.extend_from_slice(&chunk[..len]);
+
+    if self.options.strict && chunk.len() > len {
+      return Err(Error::TrailingData(self.location(QOI_BYTES_END.len())));
+    }
+
+    self.trailing_bytes += chunk.len() - len;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,206 @@
+use std::io;
+use std::mem;
+
+use crate::buffer::WriteBuffer;
+use crate::decoder::Decoder;
+use crate::error::Error;
+use crate::meta::{decoded_len, Colorspace, ImageMeta};
+use crate::options::{DecodeOptions, SupportedOptions};
+use crate::pixel::Pixel;
+use crate::srgb::{linear_to_srgb, srgb_to_linear};
+
+/// Decodes a QOI encoded image downscaled to fit within `max_width` and
+/// `max_height`, writing the thumbnail's pixel data to the provided
+/// `writer`. Upon success, `ImageMeta` data describing the thumbnail is
+/// returned.
+/// 
+/// The image's aspect ratio is preserved and it's never upscaled, so an image
+/// that already fits is decoded at its full size. Each thumbnail pixel is the
+/// average of the box of image pixels it covers, with color channels weighted
+/// by alpha so that transparent pixels don't bleed into their neighbours.
+/// Averaging is gamma-correct, it happens in linear light for images with the
+/// `Colorspace::Srgb` colorspace. Boxes are averaged as the image's rows are
+/// decoded, so memory use is proportional to a single row of the thumbnail,
+/// regardless of the image's size.
+/// 
+/// Pixels are written as RGB or RGBA to match the image's header.
+/// `Error::InvalidDimensions` is returned if `max_width` or `max_height` is 0.
+/// 
+/// ```rust
+/// use qoi::decode_thumbnail;
+/// 
+/// let image = include_bytes!("../tests/testcard_rgba_256x256.qoi");
+/// let mut thumbnail = Vec::new();
+/// 
+/// let meta = decode_thumbnail(image.as_slice(), &mut thumbnail, 64, 64)
+///   .expect("Failed to decode thumbnail");
+/// 
+/// assert_eq!((meta.width, meta.height), (64, 64));
+/// ```
+pub fn decode_thumbnail<R: io::Read, W: io::Write>(
+  reader: R,
+  writer: W,
+  max_width: u32,
+  max_height: u32,
+) -> Result<ImageMeta, Error> {
+  decode_thumbnail_with(reader, writer, max_width, max_height, &DecodeOptions::default())
+}
+
+/// Decodes a QOI encoded image downscaled to fit within `max_width` and
+/// `max_height` like `decode_thumbnail`, customized by the given `options`.
+/// The image's header is checked against `options.limits` before any pixels
+/// are decoded, so a bogus header can't make decoding a thumbnail take
+/// arbitrarily long.
+/// 
+/// Only the `background`, `format`, `premultiply`, and `strict` options are
+/// supported, `Error::UnsupportedOption` is returned for any other option.
+/// Each thumbnail pixel is averaged before it's converted as configured by
+/// the options.
+pub fn decode_thumbnail_with<R: io::Read, W: io::Write>(
+  reader: R,
+  writer: W,
+  max_width: u32,
+  max_height: u32,
+  options: &DecodeOptions,
+) -> Result<ImageMeta, Error> {
+  options.check_supported(
+    SupportedOptions::BACKGROUND
+      | SupportedOptions::FORMAT
+      | SupportedOptions::PREMULTIPLY
+      | SupportedOptions::STRICT
+  )?;
+
+  if max_width == 0 || max_height == 0 {
+    return Err(Error::InvalidDimensions);
+  }
+
+  let mut decoder = Decoder::with_options_unchecked(reader, options)?;
+  let meta = *decoder.meta();
+  let (width, height) = thumbnail_size(&meta, max_width, max_height);
+  let output = *decoder.output();
+  let pixel_len = output.format.bytes_per_pixel();
+
+  let mut sums = vec![BoxSum::default(); width as usize];
+  let mut row = vec![0; decoded_len(width as u64, pixel_len).ok_or(Error::InvalidDimensions)?];
+  let mut writer = WriteBuffer::new(writer);
+  let mut y = 0;
+
+  for thumbnail_y in 0..height {
+    let band_end = scale(thumbnail_y + 1, meta.height, height);
+
+    while y < band_end {
+      let mut x = 0;
+
+      for (thumbnail_x, sum) in sums.iter_mut().enumerate() {
+        let box_end = scale(thumbnail_x as u32 + 1, meta.width, width);
+
+        while x < box_end {
+          if let Some(pixel) = decoder.next_pixel()? {
+            sum.add(pixel, meta.colorspace);
+          }
+
+          x += 1;
+        }
+      }
+
+      y += 1;
+    }
+
+    for (sum, pixel_buf) in sums.iter_mut().zip(row.chunks_exact_mut(pixel_len)) {
+      output.write_pixel(sum.take(meta.colorspace), pixel_buf);
+    }
+
+    writer.write_all(&row)?;
+  }
+
+  writer.flush()?;
+  decoder.finish()?;
+
+  Ok(ImageMeta { width, height, ..meta })
+}
+
+// Computes the size of the thumbnail of the image described by `meta`, the
+// largest size within the given bounds that preserves the image's aspect
+// ratio, without upscaling.
+fn thumbnail_size(meta: &ImageMeta, max_width: u32, max_height: u32) -> (u32, u32) {
+  let (width, height) = (meta.width as u64, meta.height as u64);
+
+  if meta.width <= max_width && meta.height <= max_height || meta.num_pixels() == 0 {
+    return (meta.width.min(max_width), meta.height.min(max_height));
+  }
+
+  if width * max_height as u64 > height * max_width as u64 {
+    let height = (height * max_width as u64 + width / 2) / width;
+    (max_width, height.max(1) as u32)
+  } else {
+    let width = (width * max_height as u64 + height / 2) / height;
+    (width.max(1) as u32, max_height)
+  }
+}
+
+// Maps the given position along one axis of the thumbnail onto the image,
+// where `len` and `thumbnail_len` are the image's and thumbnail's lengths
+// along that axis.
+fn scale(pos: u32, len: u32, thumbnail_len: u32) -> u32 {
+  (pos as u64 * len as u64 / thumbnail_len as u64) as u32
+}
+
+// The sum of a box of image pixels, with color channels weighted by alpha.
+// The color channels of sRGB images are summed in linear light.
+#[derive(Clone, Copy, Default)]
+struct BoxSum {
+  r: f64,
+  g: f64,
+  b: f64,
+  a: u64,
+  count: u64,
+}
+
+impl BoxSum {
+  fn add(&mut self, pixel: Pixel, colorspace: Colorspace) {
+    let a = pixel.a as u64;
+    let weight = |c: u8| {
+      let c = match colorspace {
+        Colorspace::Linear => c as f64,
+        Colorspace::Srgb => srgb_to_linear(c) as f64,
+      };
+
+      c * a as f64
+    };
+
+    self.r += weight(pixel.r);
+    self.g += weight(pixel.g);
+    self.b += weight(pixel.b);
+    self.a += a;
+    self.count += 1;
+  }
+
+  // Returns the average pixel of the box, and resets the sum.
+  fn take(&mut self, colorspace: Colorspace) -> Pixel {
+    let sum = mem::take(self);
+
+    if sum.count == 0 {
+      return Pixel::default();
+    }
+
+    let color = |c: f64| {
+      if sum.a == 0 {
+        return 0;
+      }
+
+      let c = c / sum.a as f64;
+
+      match colorspace {
+        Colorspace::Linear => c.round().min(255.0) as u8,
+        Colorspace::Srgb => linear_to_srgb(c as f32),
+      }
+    };
+
+    Pixel {
+      r: color(sum.r),
+      g: color(sum.g),
+      b: color(sum.b),
+      a: ((sum.a + sum.count / 2) / sum.count) as u8,
+    }
+  }
+}
@@ -0,0 +1,161 @@
+#![cfg(feature = "async")]
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_executor::block_on;
+use futures_io::AsyncRead;
+use qoi::{decode_image_async, encode_image_async, Colorspace, Error, ImageMeta};
+
+// An `AsyncRead` implementation that only ever returns a few bytes at a time,
+// and is pending on every other poll.
+struct Trickle<'a> {
+  bytes: &'a [u8],
+  pending: bool,
+}
+
+impl AsyncRead for Trickle<'_> {
+  fn poll_read(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+    buf: &mut [u8],
+  ) -> Poll<io::Result<usize>> {
+    self.pending = !self.pending;
+
+    if self.pending {
+      cx.waker().wake_by_ref();
+      return Poll::Pending;
+    }
+
+    let len = buf.len().min(self.bytes.len()).min(3);
+    buf[..len].copy_from_slice(&self.bytes[..len]);
+    self.bytes = &self.bytes[len..];
+
+    Poll::Ready(Ok(len))
+  }
+}
+
+#[test]
+fn compare_async_decoded_image_to_reference() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let mut dest = Vec::new();
+  let meta = block_on(decode_image_async(source.as_slice(), &mut dest))
+    .expect("Failed to decode image");
+
+  assert_eq!(
+    meta,
+    ImageMeta {
+      width: 256,
+      height: 256,
+      channels: 4,
+      colorspace: Colorspace::Srgb,
+    }
+  );
+
+  assert_eq!(
+    include_bytes!("./testcard_rgba_256x256.bin").as_slice(),
+    dest.as_slice(),
+  );
+}
+
+#[test]
+fn test_async_decoding_image_from_pending_reader() {
+  let source = include_bytes!("./testcard_rgb_256x256.qoi");
+  let reader = Trickle { bytes: source, pending: false };
+  let mut dest = Vec::new();
+
+  assert!(block_on(decode_image_async(reader, &mut dest)).is_ok());
+  assert_eq!(
+    include_bytes!("./testcard_rgb_256x256.bin").as_slice(),
+    dest.as_slice(),
+  );
+}
+
+#[test]
+fn test_async_decoding_incomplete_image() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let mut dest = Vec::new();
+  let result = block_on(decode_image_async(&source[..source.len() / 2], &mut dest));
+
+  assert!(matches!(result, Err(Error::UnexpectedEof(_))));
+}
+
+#[test]
+fn compare_async_encoded_image_to_reference() {
+  let source = include_bytes!("./testcard_rgb_256x256.bin");
+  let mut dest = Vec::new();
+
+  let meta = ImageMeta {
+    width: 256,
+    height: 256,
+    channels: 3,
+    colorspace: Colorspace::Linear,
+  };
+
+  let result = block_on(encode_image_async(source.as_slice(), &mut dest, &meta));
+
+  assert!(result.is_ok());
+  assert_eq!(
+    include_bytes!("./testcard_rgb_linear_256x256.qoi").as_slice(),
+    dest.as_slice(),
+  );
+}
+
+#[test]
+fn test_async_encoding_image_from_pending_reader() {
+  let source = include_bytes!("./testcard_rgba_256x256.bin");
+  let reader = Trickle { bytes: source, pending: false };
+  let mut dest = Vec::new();
+
+  let meta = ImageMeta {
+    width: 256,
+    height: 256,
+    channels: 4,
+    colorspace: Colorspace::Srgb,
+  };
+
+  assert!(block_on(encode_image_async(reader, &mut dest, &meta)).is_ok());
+  assert_eq!(
+    include_bytes!("./testcard_rgba_256x256.qoi").as_slice(),
+    dest.as_slice(),
+  );
+}
+
+#[test]
+fn test_async_encoding_image_leaves_trailing_data_unread() {
+  let source = [include_bytes!("./testcard_rgb_256x256.bin").as_slice(), b"trailing"].concat();
+  let mut reader = source.as_slice();
+  let mut dest = Vec::new();
+
+  let meta = ImageMeta {
+    width: 256,
+    height: 256,
+    channels: 3,
+    colorspace: Colorspace::Linear,
+  };
+
+  assert!(block_on(encode_image_async(&mut reader, &mut dest, &meta)).is_ok());
+  assert_eq!(reader, b"trailing");
+  assert_eq!(
+    include_bytes!("./testcard_rgb_linear_256x256.qoi").as_slice(),
+    dest.as_slice(),
+  );
+}
+
+#[test]
+fn test_async_encoding_image_with_bad_dimensions() {
+  let source = [101, 102, 103];
+  let mut dest = Vec::new();
+
+  let meta = ImageMeta {
+    width: 999,
+    height: 1,
+    channels: 4,
+    colorspace: Colorspace::Srgb,
+  };
+
+  let result = block_on(encode_image_async(source.as_slice(), &mut dest, &meta));
+
+  assert!(matches!(result, Err(Error::UnexpectedEof(_))));
+}
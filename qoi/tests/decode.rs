@@ -1,4 +1,38 @@
-use qoi::{decode_image, Colorspace, ImageMeta};
+use std::cell::{Cell, RefCell};
+use std::io::{self, Cursor, Read, Write};
+use std::ops::ControlFlow;
+
+use qoi::{
+  decode_image,
+  decode_image_seekable,
+  decode_image_with,
+  decode_image_with_stats,
+  decode_to_f32_slice,
+  decode_to_f32_vec,
+  decode_to_slice,
+  decode_to_slice_with,
+  decode_to_vec,
+  decode_to_vec_with,
+  encode_to_vec,
+  is_qoi,
+  read_header,
+  Colorspace,
+  DecodeLimits,
+  DecodeOptions,
+  DecodeStats,
+  Decoder,
+  EndMarker,
+  Error,
+  ErrorLocation,
+  ImageMeta,
+  OpKind,
+  OpStats,
+  Pixel,
+  PixelFormat,
+  Progress,
+  Recovery,
+  QOI_HEADER_LEN,
+};
 
 #[test]
 fn test_decoding_blank_image() {
@@ -49,3 +83,994 @@ fn compare_decoded_image_to_reference() {
     dest.as_slice(),
   );
 }
+
+#[test]
+fn compare_decoded_linear_rgba_image_to_reference() {
+  let source = include_bytes!("./testcard_rgba_linear_256x256.qoi");
+  let mut dest = Vec::new();
+  let meta = decode_image(&mut source.as_slice(), &mut dest).expect("Failed to decode image");
+
+  assert_eq!(
+    meta,
+    ImageMeta {
+      width: 256,
+      height: 256,
+      channels: 4,
+      colorspace: Colorspace::Linear,
+    }
+  );
+
+  assert_eq!(
+    include_bytes!("./testcard_rgba_256x256.bin").as_slice(),
+    dest.as_slice(),
+  );
+}
+
+#[test]
+fn compare_decoded_srgb_rgb_image_to_reference() {
+  let source = include_bytes!("./testcard_rgb_256x256.qoi");
+  let mut dest = Vec::new();
+  let meta = decode_image(&mut source.as_slice(), &mut dest).expect("Failed to decode image");
+
+  assert_eq!(
+    meta,
+    ImageMeta {
+      width: 256,
+      height: 256,
+      channels: 3,
+      colorspace: Colorspace::Srgb,
+    }
+  );
+
+  assert_eq!(
+    include_bytes!("./testcard_rgb_256x256.bin").as_slice(),
+    dest.as_slice(),
+  );
+}
+
+#[test]
+fn compare_decoded_linear_rgb_image_to_reference() {
+  let source = include_bytes!("./testcard_rgb_linear_256x256.qoi");
+  let mut dest = Vec::new();
+  let meta = decode_image(&mut source.as_slice(), &mut dest).expect("Failed to decode image");
+
+  assert_eq!(
+    meta,
+    ImageMeta {
+      width: 256,
+      height: 256,
+      channels: 3,
+      colorspace: Colorspace::Linear,
+    }
+  );
+
+  assert_eq!(
+    include_bytes!("./testcard_rgb_256x256.bin").as_slice(),
+    dest.as_slice(),
+  );
+}
+
+#[test]
+fn test_decoding_image_with_pixel_format() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let reference = include_bytes!("./testcard_rgba_256x256.bin");
+
+  let formats = [
+    PixelFormat::Rgb,
+    PixelFormat::Rgba,
+    PixelFormat::Bgra,
+    PixelFormat::Argb,
+    PixelFormat::Gray,
+    PixelFormat::GrayAlpha,
+  ];
+
+  for format in formats {
+    let mut dest = Vec::new();
+    let options = DecodeOptions { format: Some(format), ..Default::default() };
+    let result = decode_image_with(source.as_slice(), &mut dest, &options);
+
+    // Alpha isn't dropped silently, formats without it require a background.
+    if !format.has_alpha() {
+      assert_eq!(result, Err(Error::MissingBackground), "{:?}", format);
+      continue;
+    }
+
+    let report = result.expect("Failed to decode image");
+
+    // Metadata still describes the image as encoded.
+    assert_eq!(report.meta.channels, 4);
+
+    let expected: Vec<u8> = reference.chunks(4).flat_map(|p| convert(format, p)).collect();
+    assert_eq!(dest, expected, "{:?}", format);
+  }
+}
+
+#[test]
+fn test_decoding_rgb_image_as_rgba() {
+  let source = include_bytes!("./testcard_rgb_256x256.qoi");
+  let mut dest = Vec::new();
+  let options = DecodeOptions { format: Some(PixelFormat::Rgba), ..Default::default() };
+  let report = decode_image_with(source.as_slice(), &mut dest, &options)
+    .expect("Failed to decode image");
+
+  assert_eq!(report.meta.channels, 3);
+
+  let expected: Vec<u8> = include_bytes!("./testcard_rgb_256x256.bin")
+    .chunks(3)
+    .flat_map(|p| [p[0], p[1], p[2], 255])
+    .collect();
+
+  assert_eq!(dest, expected);
+}
+
+#[test]
+fn compare_image_decoded_to_vec_to_reference() {
+  let source = include_bytes!("./testcard_rgb_256x256.qoi");
+  let (meta, dest) = decode_to_vec(source).expect("Failed to decode image");
+
+  assert_eq!(meta.channels, 3);
+  assert_eq!(
+    include_bytes!("./testcard_rgb_256x256.bin").as_slice(),
+    dest.as_slice(),
+  );
+}
+
+#[test]
+fn test_decoding_image_with_unbuffered_writer() {
+  struct CountingWriter {
+    inner: Vec<u8>,
+    writes: usize,
+  }
+
+  impl Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+      self.writes += 1;
+      self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+      Ok(())
+    }
+  }
+
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let reference = include_bytes!("./testcard_rgba_256x256.bin");
+  let mut writer = CountingWriter { inner: Vec::new(), writes: 0 };
+
+  decode_image(source.as_slice(), &mut writer).expect("Failed to decode image");
+
+  assert_eq!(writer.inner, reference);
+  assert!(writer.writes < 64, "{} writes", writer.writes);
+}
+
+#[test]
+fn test_writing_pixels_decoded_before_error() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let reference = include_bytes!("./testcard_rgba_256x256.bin");
+  let truncated = &source[..source.len() / 2];
+  let mut dest = Vec::new();
+
+  match decode_image(truncated, &mut dest) {
+    Err(Error::UnexpectedEof(Some(location))) => {
+      let decoded = location.pixel.expect("Expected pixel location");
+      let decoded = (decoded.1 * 256 + decoded.0) as usize;
+
+      assert_eq!(dest.len(), decoded * 4);
+      assert_eq!(dest.as_slice(), &reference[..decoded * 4]);
+    },
+    result => panic!("Unexpected result {:?}", result),
+  }
+}
+
+#[test]
+fn test_decoding_image_with_stats() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let reference = include_bytes!("./testcard_rgba_256x256.bin");
+  let mut dest = Vec::new();
+
+  let (meta, stats) =
+    decode_image_with_stats(source.as_slice(), &mut dest).expect("Failed to decode image");
+
+  assert_eq!(dest, reference);
+
+  // Every byte between the header and end marker belongs to an op.
+  let total = stats.total();
+  assert_eq!(total.bytes, (source.len() - QOI_HEADER_LEN - 8) as u64);
+
+  // Every pixel is either decoded from its own op or is part of a run.
+  let run_pixels: u64 =
+    stats.run_lengths.iter().enumerate().map(|(i, &runs)| runs * (i as u64 + 1)).sum();
+  assert_eq!(total.count - stats.run.count + run_pixels, meta.num_pixels());
+  assert_eq!(stats.run_lengths.iter().sum::<u64>(), stats.run.count);
+
+  for (kind, len) in [
+    (OpKind::Color, 1),
+    (OpKind::Index, 1),
+    (OpKind::Luma, 2),
+    (OpKind::Rgb, 4),
+    (OpKind::Rgba, 5),
+    (OpKind::Run, 1),
+  ] {
+    assert_eq!(stats.op(kind).bytes, stats.op(kind).count * len);
+  }
+
+  let ratio = stats.index_hit_ratio();
+  assert!(ratio > 0.0 && ratio < 1.0);
+}
+
+#[test]
+fn test_collecting_stats_of_a_run() {
+  let meta = ImageMeta { width: 3, height: 1, channels: 4, colorspace: Colorspace::Srgb };
+  let source = encode_to_vec(&[0, 0, 0, 255].repeat(3), &meta).expect("Failed to encode image");
+
+  let (_, stats) =
+    decode_image_with_stats(source.as_slice(), io::sink()).expect("Failed to decode image");
+
+  let mut expected = DecodeStats {
+    run: OpStats { count: 1, bytes: 1 },
+    ..Default::default()
+  };
+  expected.run_lengths[2] = 1;
+
+  assert_eq!(stats, expected);
+  assert_eq!(stats.index_hit_ratio(), 0.0);
+}
+
+#[test]
+fn test_decoding_image_to_slice() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let reference = include_bytes!("./testcard_rgba_256x256.bin");
+
+  // Bytes past the decoded pixels are left untouched.
+  let mut dest = vec![7; reference.len() + 1];
+  let meta = decode_to_slice(source, &mut dest).expect("Failed to decode image");
+
+  assert_eq!(meta.width, 256);
+  assert_eq!(&dest[..reference.len()], reference.as_slice());
+  assert_eq!(dest[reference.len()], 7);
+}
+
+#[test]
+fn test_decoding_image_to_small_slice() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let mut dest = vec![0; 256 * 256];
+
+  assert!(matches!(
+    decode_to_slice(source, &mut dest),
+    Err(Error::BufferTooSmall(len)) if len == 256 * 256 * 4
+  ));
+}
+
+#[test]
+fn test_decoding_image_to_slice_with_stride() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let reference = include_bytes!("./testcard_rgba_256x256.bin");
+  let options = DecodeOptions { stride: Some(1280), ..Default::default() };
+
+  // The last row isn't padded.
+  let mut dest = vec![7; 1280 * 255 + 1024];
+  decode_to_slice_with(source, &mut dest, &options).expect("Failed to decode image");
+
+  for (row, expected) in dest.chunks(1280).zip(reference.chunks(1024)) {
+    assert_eq!(&row[..1024], expected);
+    assert!(row[1024..].iter().all(|&byte| byte == 7));
+  }
+
+  assert_eq!(
+    decode_to_slice_with(source, &mut dest[1..], &options),
+    Err(Error::BufferTooSmall(1280 * 255 + 1024))
+  );
+
+  let options = DecodeOptions { stride: Some(1023), ..Default::default() };
+  assert_eq!(decode_to_slice_with(source, &mut dest, &options), Err(Error::InvalidStride));
+}
+
+#[test]
+fn test_decoding_flipped_image() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let reference = include_bytes!("./testcard_rgba_256x256.bin");
+  let flipped: Vec<u8> = reference.chunks(256 * 4).rev().flatten().copied().collect();
+  let options = DecodeOptions { flip_vertically: true, ..Default::default() };
+
+  let mut to_slice = vec![0; reference.len()];
+  decode_to_slice_with(source, &mut to_slice, &options).expect("Failed to decode image");
+
+  let mut to_writer = Cursor::new(Vec::new());
+  decode_image_seekable(source.as_slice(), &mut to_writer, &options).expect("Failed to decode image");
+
+  assert_eq!(to_slice, flipped);
+  assert_eq!(to_writer.position(), flipped.len() as u64);
+  assert_eq!(to_writer.into_inner(), flipped);
+}
+
+#[test]
+fn test_decoding_incomplete_image_from_slice() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+
+  assert!(decode_to_vec(&source[..source.len() / 2]).is_err());
+  assert!(decode_to_vec(&source[..10]).is_err());
+}
+
+#[test]
+fn test_compositing_image_onto_background() {
+  let pixels = [255, 255, 255, 128, 10, 20, 30, 0, 40, 50, 60, 255];
+  let background = Pixel { r: 0, g: 0, b: 100, a: 0 };
+  let options = DecodeOptions {
+    format: Some(PixelFormat::Rgb),
+    background: Some(background),
+    ..Default::default()
+  };
+
+  for (colorspace, expected) in [
+    (Colorspace::Srgb, [188, 188, 198, 0, 0, 100, 40, 50, 60]),
+    (Colorspace::Linear, [128, 128, 178, 0, 0, 100, 40, 50, 60]),
+  ] {
+    let meta = ImageMeta { width: 3, height: 1, channels: 4, colorspace };
+    let source = encode_to_vec(&pixels, &meta).expect("Failed to encode image");
+    let mut dest = Vec::new();
+
+    decode_image_with(source.as_slice(), &mut dest, &options).expect("Failed to decode image");
+
+    assert_eq!(dest, expected, "{:?}", colorspace);
+  }
+}
+
+#[test]
+fn test_dropping_alpha_without_background() {
+  let rgba = include_bytes!("./testcard_rgba_256x256.qoi");
+  let rgb = include_bytes!("./testcard_rgb_256x256.qoi");
+  let mut dest = vec![0; 256 * 256 * 3];
+
+  for format in [PixelFormat::Rgb, PixelFormat::Gray] {
+    let options = DecodeOptions { format: Some(format), ..Default::default() };
+
+    assert_eq!(
+      decode_to_slice_with(rgba, &mut dest, &options),
+      Err(Error::MissingBackground)
+    );
+
+    let decoder = Decoder::with_options(rgba.as_slice(), &options);
+    assert!(matches!(decoder, Err(Error::MissingBackground)));
+
+    // Images without alpha have nothing to composite.
+    assert!(decode_to_slice_with(rgb, &mut dest, &options).is_ok());
+
+    let options = DecodeOptions { background: Some(Pixel::default()), ..options };
+    assert!(decode_to_slice_with(rgba, &mut dest, &options).is_ok());
+  }
+}
+
+#[test]
+fn compare_composited_image_to_reference() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let reference = include_bytes!("./testcard_rgba_256x256.bin");
+  let background = Pixel { r: 200, g: 100, b: 50, a: 255 };
+  let options = DecodeOptions {
+    format: Some(PixelFormat::Rgb),
+    background: Some(background),
+    ..Default::default()
+  };
+
+  let to_linear = |c: f64| {
+    if c <= 0.04045 {
+      c / 12.92
+    } else {
+      ((c + 0.055) / 1.055).powf(2.4)
+    }
+  };
+
+  let to_srgb = |c: f64| {
+    if c <= 0.0031308 {
+      c * 12.92
+    } else {
+      1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+  };
+
+  let mut dest = Vec::new();
+  decode_image_with(source.as_slice(), &mut dest, &options).expect("Failed to decode image");
+
+  for (pixel, composited) in reference.chunks(4).zip(dest.chunks(3)) {
+    let alpha = pixel[3] as f64 / 255.0;
+
+    for (c, bg) in [background.r, background.g, background.b].into_iter().enumerate() {
+      let blended = to_linear(pixel[c] as f64 / 255.0) * alpha
+        + to_linear(bg as f64 / 255.0) * (1.0 - alpha);
+      let expected = (to_srgb(blended) * 255.0).round();
+
+      assert!((composited[c] as f64 - expected).abs() <= 1.0);
+    }
+  }
+}
+
+#[test]
+fn compare_image_decoded_to_f32_to_reference() {
+  let reference = include_bytes!("./testcard_rgba_256x256.bin");
+  let srgb = include_bytes!("./testcard_rgba_256x256.qoi");
+  let linear = include_bytes!("./testcard_rgba_linear_256x256.qoi");
+
+  let to_linear = |c: f64| {
+    if c <= 0.04045 {
+      c / 12.92
+    } else {
+      ((c + 0.055) / 1.055).powf(2.4)
+    }
+  };
+
+  let linearize = DecodeOptions { linearize: true, ..Default::default() };
+  let (_, normalized) = decode_to_f32_vec(srgb, &DecodeOptions::default())
+    .expect("Failed to decode image");
+  let (_, linearized) = decode_to_f32_vec(srgb, &linearize).expect("Failed to decode image");
+  let (_, passed) = decode_to_f32_vec(linear, &linearize).expect("Failed to decode image");
+
+  assert_eq!(normalized.len(), reference.len());
+  assert_eq!(normalized, passed);
+
+  for (i, &value) in reference.iter().enumerate() {
+    let value = value as f64 / 255.0;
+    let expected = if i % 4 == 3 { value } else { to_linear(value) };
+
+    assert_eq!(normalized[i], value as f32);
+    assert_eq!(linearized[i], expected as f32);
+  }
+
+  assert!(matches!(
+    decode_to_f32_slice(srgb, &mut [0.0; 16], &linearize),
+    Err(Error::BufferTooSmall(len)) if len == 256 * 256 * 4
+  ));
+
+  // Alpha stays normalized wherever the format places it.
+  let argb = DecodeOptions { format: Some(PixelFormat::Argb), ..linearize };
+  let (report, values) = decode_to_f32_vec(srgb, &argb).expect("Failed to decode image");
+
+  assert_eq!(report.end_marker, EndMarker::Valid);
+  assert_eq!(values[0], linearized[3]);
+  assert_eq!(values[1..4], linearized[..3]);
+
+  // Each value takes up 4 bytes.
+  let limits = DecodeLimits { max_bytes: Some(256 * 256 * 4), ..Default::default() };
+  let options = DecodeOptions { limits, ..Default::default() };
+  assert!(matches!(decode_to_f32_vec(srgb, &options), Err(Error::LimitExceeded)));
+}
+
+#[test]
+fn test_premultiplying_and_compositing_linearized_f32_values() {
+  let pixels = [200, 200, 200, 128];
+  let meta = ImageMeta { width: 1, height: 1, channels: 4, colorspace: Colorspace::Srgb };
+  let source = encode_to_vec(&pixels, &meta).expect("Failed to encode image");
+
+  // 200 in sRGB is about 0.5776 in linear light, and 128 is about 0.502 alpha.
+  let linear = ((200.0 / 255.0 + 0.055) / 1.055f64).powf(2.4);
+  let alpha = 128.0 / 255.0;
+  let assert_close = |value: f32, expected: f64| {
+    assert!((value as f64 - expected).abs() < 1e-6, "{} != {}", value, expected);
+  };
+
+  let options = DecodeOptions { linearize: true, premultiply: true, ..Default::default() };
+  let (_, values) = decode_to_f32_vec(&source, &options).expect("Failed to decode image");
+
+  assert_eq!(values.len(), 4);
+  assert_close(values[0], linear * alpha);
+  assert!((values[0] - 0.2899).abs() < 1e-4);
+  assert_eq!(values[..3], [values[0]; 3]);
+  assert_eq!(values[3], 128.0 / 255.0);
+
+  let options = DecodeOptions {
+    background: Some(Pixel { r: 255, g: 255, b: 255, a: 255 }),
+    format: Some(PixelFormat::Rgb),
+    linearize: true,
+    ..Default::default()
+  };
+  let (_, values) = decode_to_f32_vec(&source, &options).expect("Failed to decode image");
+
+  assert_eq!(values.len(), 3);
+  assert_close(values[0], linear * alpha + (1.0 - alpha));
+  assert_eq!(values, [values[0]; 3]);
+}
+
+#[test]
+fn test_decoding_image_with_premultiplied_alpha() {
+  let pixels = [255, 255, 255, 128, 10, 20, 30, 0, 40, 50, 60, 255];
+  let meta = ImageMeta { width: 3, height: 1, channels: 4, colorspace: Colorspace::Srgb };
+  let source = encode_to_vec(&pixels, &meta).expect("Failed to encode image");
+  let options = DecodeOptions { premultiply: true, ..Default::default() };
+  let mut dest = Vec::new();
+
+  decode_image_with(source.as_slice(), &mut dest, &options).expect("Failed to decode image");
+
+  assert_eq!(dest, [128, 128, 128, 128, 0, 0, 0, 0, 40, 50, 60, 255]);
+}
+
+#[test]
+fn compare_premultiplied_image_to_reference() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let reference = include_bytes!("./testcard_rgba_256x256.bin");
+  let options = DecodeOptions { premultiply: true, ..Default::default() };
+  let mut dest = Vec::new();
+
+  decode_image_with(source.as_slice(), &mut dest, &options).expect("Failed to decode image");
+
+  for (pixel, premultiplied) in reference.chunks(4).zip(dest.chunks(4)) {
+    let alpha = pixel[3] as f64 / 255.0;
+
+    for c in 0..3 {
+      assert_eq!(premultiplied[c] as f64, (pixel[c] as f64 * alpha).round());
+    }
+
+    assert_eq!(premultiplied[3], pixel[3]);
+  }
+}
+
+#[test]
+fn test_locating_decode_errors() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let header = Err(Error::UnexpectedEof(Some(ErrorLocation::default())));
+
+  assert_eq!(decode_image(&source[..10], Vec::new()).map(|_| ()), header);
+  assert_eq!(read_header(&source[..10]).map(|_| ()), header);
+  assert_eq!(decode_to_slice(&source[..10], &mut []).map(|_| ()), header);
+
+  let incomplete = include_bytes!("./incomplete_rgb.qoi");
+  let expected = Err(Error::UnexpectedEof(Some(ErrorLocation {
+    offset: QOI_HEADER_LEN as u64,
+    pixel: Some((0, 0)),
+    last_op: None,
+  })));
+
+  assert_eq!(decode_image(incomplete.as_slice(), Vec::new()).map(|_| ()), expected);
+  assert_eq!(decode_to_slice(incomplete, &mut [0; 99 * 3]).map(|_| ()), expected);
+
+  // Every decoder reports the same location for the same truncated image.
+  let truncated = &source[..source.len() / 2];
+  let err = decode_image(truncated, Vec::new()).expect_err("Decoded a truncated image");
+  let mut dest = vec![0; 256 * 256 * 4];
+
+  assert_eq!(decode_to_slice(truncated, &mut dest).expect_err("Decoded a truncated image"), err);
+
+  match err {
+    Error::UnexpectedEof(Some(location)) => {
+      assert!(location.offset < truncated.len() as u64);
+      assert!(location.pixel.is_some());
+      assert!(location.last_op.is_some());
+      assert!(err.to_string().contains(&format!("at byte {}", location.offset)));
+    }
+    _ => panic!("Unexpected error {}", err),
+  }
+}
+
+#[test]
+fn test_reading_image_header() {
+  let source = include_bytes!("./testcard_rgb_linear_256x256.qoi");
+  let mut reader = source.as_slice();
+  let meta = read_header(&mut reader).expect("Failed to read header");
+
+  assert_eq!(
+    meta,
+    ImageMeta {
+      width: 256,
+      height: 256,
+      channels: 3,
+      colorspace: Colorspace::Linear,
+    }
+  );
+
+  assert_eq!(meta.decoded_len(), Some(256 * 256 * 3));
+
+  // Only the header is read.
+  assert_eq!(reader.len(), source.len() - QOI_HEADER_LEN);
+}
+
+#[test]
+fn test_checking_if_bytes_are_qoi() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+
+  assert!(is_qoi(source));
+  assert!(is_qoi(include_bytes!("./incomplete_rgb.qoi")));
+  assert!(!is_qoi(&source[..QOI_HEADER_LEN - 1]));
+  assert!(!is_qoi(include_bytes!("./testcard_rgba_256x256.bin")));
+}
+
+#[test]
+fn test_decoding_image_within_limits() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let options = DecodeOptions {
+    limits: DecodeLimits {
+      max_width: Some(256),
+      max_height: Some(256),
+      max_pixels: Some(256 * 256),
+      max_bytes: Some(256 * 256 * 4),
+    },
+    ..Default::default()
+  };
+
+  assert!(decode_image_with(source.as_slice(), Vec::new(), &options).is_ok());
+}
+
+#[test]
+fn test_decoding_image_exceeding_limits() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let limits = [
+    DecodeLimits { max_width: Some(255), ..Default::default() },
+    DecodeLimits { max_height: Some(255), ..Default::default() },
+    DecodeLimits { max_pixels: Some(256 * 256 - 1), ..Default::default() },
+    DecodeLimits { max_bytes: Some(256 * 256 * 4 - 1), ..Default::default() },
+  ];
+
+  for limits in limits {
+    let mut dest = Vec::new();
+    let options = DecodeOptions { limits, ..Default::default() };
+    let result = decode_image_with(source.as_slice(), &mut dest, &options);
+
+    assert!(matches!(result, Err(Error::LimitExceeded)), "{:?}", limits);
+    assert!(dest.is_empty());
+  }
+
+  // The byte limit applies to the pixel format being decoded to.
+  let options = DecodeOptions {
+    background: Some(Pixel::default()),
+    format: Some(PixelFormat::Gray),
+    limits: DecodeLimits { max_bytes: Some(256 * 256), ..Default::default() },
+    ..Default::default()
+  };
+
+  assert!(decode_image_with(source.as_slice(), Vec::new(), &options).is_ok());
+}
+
+#[test]
+fn test_decoding_image_bomb() {
+  // A header that claims to be a 4 billion pixel image, followed by nothing.
+  let mut source = Vec::new();
+  source.extend_from_slice(b"qoif");
+  source.extend_from_slice(&u32::MAX.to_be_bytes());
+  source.extend_from_slice(&u32::MAX.to_be_bytes());
+  source.extend_from_slice(&[4, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+  let meta = read_header(source.as_slice()).expect("Failed to read header");
+  assert_eq!(meta.num_pixels(), u32::MAX as u64 * u32::MAX as u64);
+
+  let options = DecodeOptions {
+    limits: DecodeLimits { max_pixels: Some(1 << 24), ..Default::default() },
+    ..Default::default()
+  };
+
+  assert!(matches!(
+    decode_image_with(source.as_slice(), Vec::new(), &options),
+    Err(Error::LimitExceeded)
+  ));
+
+  assert!(decode_to_vec(&source).is_err());
+}
+
+#[test]
+fn test_strictly_decoding_image() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let options = DecodeOptions { strict: true, ..Default::default() };
+  let report = decode_image_with(source.as_slice(), Vec::new(), &options)
+    .expect("Failed to decode image");
+
+  assert_eq!(report.end_marker, EndMarker::Valid);
+}
+
+#[test]
+fn test_strictly_decoding_image_with_bad_end_marker() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let options = DecodeOptions { strict: true, ..Default::default() };
+
+  let mut trailing = source.to_vec();
+  trailing.push(0);
+
+  match decode_image_with(trailing.as_slice(), Vec::new(), &options) {
+    Err(Error::TrailingData(location)) => {
+      assert_eq!(location.offset, source.len() as u64);
+      assert_eq!(location.pixel, None);
+    }
+    result => panic!("Expected trailing data, got {:?}", result),
+  }
+
+  let report = decode_image_with(trailing.as_slice(), Vec::new(), &DecodeOptions::default())
+    .expect("Failed to decode image");
+  assert_eq!(report.trailing_bytes, None);
+  assert!(report.has_trailing_data);
+
+  let truncated = &source[..source.len() - 3];
+
+  assert!(matches!(
+    decode_image_with(truncated, Vec::new(), &options),
+    Err(Error::UnexpectedEof(_))
+  ));
+
+  let mut invalid = source.to_vec();
+  invalid[source.len() - 1] = 2;
+
+  match decode_image_with(invalid.as_slice(), Vec::new(), &options) {
+    Err(Error::InvalidEndMarker(location)) => {
+      assert_eq!(location.offset, source.len() as u64 - 8);
+      assert_eq!(location.pixel, None);
+    }
+    result => panic!("Expected an invalid end marker, got {:?}", result),
+  }
+}
+
+#[test]
+fn test_strictly_decoding_image_from_slice() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let options = DecodeOptions { strict: true, ..Default::default() };
+  let mut dest = vec![0; 256 * 256 * 4];
+
+  let report = decode_to_slice_with(source, &mut dest, &options).expect("Failed to decode image");
+  assert_eq!(report.end_marker, EndMarker::Valid);
+
+  let trailing = [source.as_slice(), &[0]].concat();
+  let truncated = &source[..source.len() - 3];
+  let invalid = [&source[..source.len() - 1], &[2]].concat();
+
+  assert!(matches!(
+    decode_to_slice_with(&trailing, &mut dest, &options),
+    Err(Error::TrailingData(location)) if location.offset == source.len() as u64
+  ));
+  assert!(matches!(
+    decode_to_slice_with(truncated, &mut dest, &options),
+    Err(Error::UnexpectedEof(Some(_)))
+  ));
+  assert!(matches!(
+    decode_to_slice_with(&invalid, &mut dest, &options),
+    Err(Error::InvalidEndMarker(location)) if location.offset == source.len() as u64 - 8
+  ));
+
+  let trailing = [source.as_slice(), &[0; 3]].concat();
+  let report = decode_to_slice_with(&trailing, &mut dest, &DecodeOptions::default())
+    .expect("Failed to decode image");
+  assert_eq!(report.end_marker, EndMarker::Valid);
+  assert_eq!(report.trailing_bytes, Some(3));
+  assert!(report.has_trailing_data);
+
+  let report = decode_to_slice_with(truncated, &mut dest, &DecodeOptions::default())
+    .expect("Failed to decode image");
+  assert_eq!(report.end_marker, EndMarker::Truncated(5));
+}
+
+#[test]
+fn test_leniently_decoding_image_with_bad_end_marker() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let reference = include_bytes!("./testcard_rgba_256x256.bin");
+  let options = DecodeOptions::default();
+
+  let cases = [
+    (source[..source.len() - 3].to_vec(), EndMarker::Truncated(5)),
+    (source[..source.len() - 8].to_vec(), EndMarker::Truncated(0)),
+    ([&source[..source.len() - 1], &[2]].concat(), EndMarker::Invalid),
+    ([source.as_slice(), &[0]].concat(), EndMarker::Valid),
+  ];
+
+  for (source, end_marker) in cases {
+    let mut dest = Vec::new();
+    let report = decode_image_with(source.as_slice(), &mut dest, &options)
+      .expect("Failed to decode image");
+
+    assert_eq!(report.end_marker, end_marker);
+    assert_eq!(dest.as_slice(), reference.as_slice());
+  }
+}
+
+#[test]
+fn test_decoding_with_unsupported_options() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let callback = |_, _| ControlFlow::Continue(());
+  let progress = Some(Progress { callback: &callback, interval: 1 });
+
+  let stride = DecodeOptions { stride: Some(2048), ..Default::default() };
+  let flip = DecodeOptions { flip_vertically: true, ..Default::default() };
+  let with_progress = DecodeOptions { progress, ..Default::default() };
+  let linearize = DecodeOptions { linearize: true, ..Default::default() };
+
+  assert!(matches!(
+    decode_image_with(source.as_slice(), io::sink(), &stride),
+    Err(Error::UnsupportedOption("stride"))
+  ));
+  assert!(matches!(
+    decode_image_with(source.as_slice(), io::sink(), &flip),
+    Err(Error::UnsupportedOption("flip_vertically"))
+  ));
+  assert!(matches!(
+    Decoder::with_options(source.as_slice(), &with_progress),
+    Err(Error::UnsupportedOption("progress"))
+  ));
+  assert!(matches!(
+    decode_to_slice_with(source, &mut [0; 256 * 256 * 4], &linearize),
+    Err(Error::UnsupportedOption("linearize"))
+  ));
+}
+
+#[test]
+fn test_recovering_truncated_image() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let expected = include_bytes!("./testcard_rgba_256x256.bin");
+  let truncated = &source[..source.len() / 2];
+
+  let options = DecodeOptions { recovery: Some(Recovery::Truncate), ..Default::default() };
+  let mut dest = Vec::new();
+  let report = decode_image_with(truncated, &mut dest, &options).expect("Failed to recover image");
+  let decoded = report.pixels_decoded as usize;
+
+  assert!(decoded > 0 && decoded < 256 * 256);
+  assert!(matches!(report.error, Some(Error::UnexpectedEof(Some(_)))));
+  assert_eq!(report.end_marker, EndMarker::Truncated(0));
+  assert_eq!(dest.as_slice(), &expected[..decoded * 4]);
+
+  let fill = Pixel { r: 255, g: 0, b: 255, a: 255 };
+  let options = DecodeOptions { recovery: Some(Recovery::Fill(fill)), ..Default::default() };
+  let mut dest = Vec::new();
+  let report = decode_image_with(truncated, &mut dest, &options).expect("Failed to recover image");
+
+  assert_eq!(report.pixels_decoded as usize, decoded);
+  assert_eq!(dest.len(), expected.len());
+  assert_eq!(&dest[..decoded * 4], &expected[..decoded * 4]);
+  assert!(dest[decoded * 4..].chunks(4).all(|p| p == [255, 0, 255, 255]));
+}
+
+#[test]
+fn test_recovering_truncated_image_from_slice() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let expected = include_bytes!("./testcard_rgba_256x256.bin");
+  let truncated = &source[..source.len() / 2];
+
+  let options = DecodeOptions { recovery: Some(Recovery::Truncate), ..Default::default() };
+  let mut dest = vec![0; expected.len()];
+  let report = decode_to_slice_with(truncated, &mut dest, &options)
+    .expect("Failed to recover image");
+  let decoded = report.pixels_decoded as usize;
+
+  assert!(decoded > 0 && decoded < 256 * 256);
+  assert!(matches!(report.error, Some(Error::UnexpectedEof(Some(_)))));
+  assert_eq!(report.end_marker, EndMarker::Truncated(0));
+  assert_eq!(&dest[..decoded * 4], &expected[..decoded * 4]);
+  assert!(dest[decoded * 4..].iter().all(|&value| value == 0));
+
+  let fill = Pixel { r: 255, g: 0, b: 255, a: 255 };
+  let options = DecodeOptions { recovery: Some(Recovery::Fill(fill)), ..Default::default() };
+  let report = decode_to_slice_with(truncated, &mut dest, &options)
+    .expect("Failed to recover image");
+
+  assert_eq!(report.pixels_decoded as usize, decoded);
+  assert_eq!(&dest[..decoded * 4], &expected[..decoded * 4]);
+  assert!(dest[decoded * 4..].chunks(4).all(|p| p == [255, 0, 255, 255]));
+}
+
+#[test]
+fn test_recovering_truncated_image_to_vec() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let expected = include_bytes!("./testcard_rgba_256x256.bin");
+
+  // Too short to possibly hold all of the image's pixels, so it's rejected
+  // up front unless recovering.
+  let truncated = &source[..QOI_HEADER_LEN + 100];
+  assert_eq!(decode_to_vec(truncated), Err(Error::UnexpectedEof(None)));
+
+  let fill = Pixel { r: 255, g: 0, b: 255, a: 255 };
+  let options = DecodeOptions { recovery: Some(Recovery::Fill(fill)), ..Default::default() };
+  let (report, dest) = decode_to_vec_with(truncated, &options).expect("Failed to recover image");
+  let decoded = report.pixels_decoded as usize;
+
+  assert!(decoded > 0 && decoded < 256 * 256);
+  assert!(matches!(report.error, Some(Error::UnexpectedEof(Some(_)))));
+  assert_eq!(dest.len(), expected.len());
+  assert_eq!(&dest[..decoded * 4], &expected[..decoded * 4]);
+  assert!(dest[decoded * 4..].chunks(4).all(|p| p == [255, 0, 255, 255]));
+
+  let limits = DecodeLimits { max_pixels: Some(256 * 255), ..Default::default() };
+  let options = DecodeOptions { limits, ..options };
+  assert_eq!(decode_to_vec_with(truncated, &options), Err(Error::LimitExceeded));
+}
+
+#[test]
+fn test_recovering_complete_image() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let options = DecodeOptions { recovery: Some(Recovery::Truncate), ..Default::default() };
+  let report = decode_image_with(source.as_slice(), Vec::new(), &options)
+    .expect("Failed to decode image");
+
+  assert_eq!(report.pixels_decoded, 256 * 256);
+  assert_eq!(report.error, None);
+  assert_eq!(report.end_marker, EndMarker::Valid);
+}
+
+#[test]
+fn test_not_recovering_from_io_errors() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let failing = source[..source.len() / 2].chain(FailingReader);
+
+  let fill = Pixel { r: 255, g: 0, b: 255, a: 255 };
+  let options = DecodeOptions { recovery: Some(Recovery::Fill(fill)), ..Default::default() };
+  let result = decode_image_with(failing, io::sink(), &options);
+
+  assert!(matches!(result, Err(Error::IoError(e)) if e.kind() == io::ErrorKind::BrokenPipe));
+}
+
+#[test]
+fn test_reporting_decode_progress() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let reference = include_bytes!("./testcard_rgba_256x256.bin");
+  let calls = RefCell::new(Vec::new());
+
+  let callback = |rows_done, rows_total| {
+    calls.borrow_mut().push((rows_done, rows_total));
+    ControlFlow::Continue(())
+  };
+
+  let options = DecodeOptions {
+    progress: Some(Progress { callback: &callback, interval: 100 }),
+    ..Default::default()
+  };
+
+  let mut dest = Vec::new();
+  decode_image_with(source.as_slice(), &mut dest, &options).expect("Failed to decode image");
+
+  assert_eq!(dest, reference);
+  assert_eq!(*calls.borrow(), [(100, 256), (200, 256), (256, 256)]);
+
+  let options = DecodeOptions { flip_vertically: true, ..options };
+  let mut dest = Cursor::new(Vec::new());
+  calls.borrow_mut().clear();
+
+  decode_image_seekable(source.as_slice(), &mut dest, &options).expect("Failed to decode image");
+
+  assert_eq!(*calls.borrow(), [(100, 256), (200, 256), (256, 256)]);
+
+  let mut dest = vec![0; reference.len()];
+  calls.borrow_mut().clear();
+
+  decode_to_slice_with(source, &mut dest, &options).expect("Failed to decode image");
+
+  assert_eq!(*calls.borrow(), [(100, 256), (200, 256), (256, 256)]);
+}
+
+#[test]
+fn test_cancelling_decode() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let calls = Cell::new(0);
+
+  let callback = |rows_done, _| {
+    calls.set(calls.get() + 1);
+
+    match rows_done {
+      0..=99 => ControlFlow::Continue(()),
+      _ => ControlFlow::Break(()),
+    }
+  };
+
+  let options = DecodeOptions {
+    progress: Some(Progress { callback: &callback, interval: 50 }),
+    ..Default::default()
+  };
+
+  let result = decode_image_with(source.as_slice(), io::sink(), &options);
+
+  assert_eq!(result, Err(Error::Cancelled));
+  assert_eq!(calls.get(), 2);
+}
+
+// Converts a single RGBA pixel into the given format.
+fn convert(format: PixelFormat, p: &[u8]) -> Vec<u8> {
+  match format {
+    PixelFormat::Rgb => vec![p[0], p[1], p[2]],
+    PixelFormat::Rgba => p.to_vec(),
+    PixelFormat::Bgra => vec![p[2], p[1], p[0], p[3]],
+    PixelFormat::Argb => vec![p[3], p[0], p[1], p[2]],
+    PixelFormat::Gray => vec![luma(p)],
+    PixelFormat::GrayAlpha => vec![luma(p), p[3]],
+  }
+}
+
+fn luma(pixel: &[u8]) -> u8 {
+  let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(u32::from);
+  ((r * 299 + g * 587 + b * 114 + 500) / 1000) as u8
+}
+
+// A reader that always fails.
+struct FailingReader;
+
+impl Read for FailingReader {
+  fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+    Err(io::ErrorKind::BrokenPipe.into())
+  }
+}
@@ -0,0 +1,80 @@
+use qoi::{DecodeOptions, Decoder, EndMarker, Error, Pixel, PixelFormat};
+
+#[test]
+fn compare_rows_decoded_to_reference() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let reference = include_bytes!("./testcard_rgba_256x256.bin");
+  let mut decoder = Decoder::new(source.as_slice()).expect("Failed to decode header");
+
+  assert_eq!(decoder.meta().width, 256);
+  assert_eq!(decoder.row_len(), 256 * 4);
+
+  let mut row = vec![0; decoder.row_len()];
+  let mut rows = reference.chunks(256 * 4);
+  let mut expected_y = 0;
+
+  while let Some(y) = decoder.next_row(&mut row).expect("Failed to decode row") {
+    assert_eq!(y, expected_y);
+    assert_eq!(Some(row.as_slice()), rows.next());
+    expected_y += 1;
+  }
+
+  assert_eq!(expected_y, 256);
+  assert_eq!(decoder.next_row(&mut row).ok(), Some(None));
+
+  let report = decoder.finish().expect("Failed to finish decoding");
+  assert_eq!(report.end_marker, EndMarker::Valid);
+}
+
+#[test]
+fn test_decoding_rows_with_pixel_format() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let options = DecodeOptions {
+    background: Some(Pixel::default()),
+    format: Some(PixelFormat::Gray),
+    ..Default::default()
+  };
+  let mut decoder = Decoder::with_options(source.as_slice(), &options)
+    .expect("Failed to decode header");
+
+  assert_eq!(decoder.format(), PixelFormat::Gray);
+  assert_eq!(decoder.row_len(), 256);
+
+  let mut row = vec![0; decoder.row_len()];
+  assert_eq!(decoder.next_row(&mut row).ok(), Some(Some(0)));
+}
+
+#[test]
+fn test_decoding_row_into_small_buffer() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let mut decoder = Decoder::new(source.as_slice()).expect("Failed to decode header");
+  let mut row = vec![0; decoder.row_len() - 1];
+
+  assert!(matches!(
+    decoder.next_row(&mut row),
+    Err(Error::BufferTooSmall(len)) if len == 256 * 4
+  ));
+}
+
+#[test]
+fn test_finishing_partially_decoded_image() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let options = DecodeOptions { strict: true, ..Default::default() };
+  let mut decoder = Decoder::with_options(source.as_slice(), &options)
+    .expect("Failed to decode header");
+
+  let mut row = vec![0; decoder.row_len()];
+  decoder.next_row(&mut row).expect("Failed to decode row");
+
+  let report = decoder.finish().expect("Failed to finish decoding");
+  assert_eq!(report.end_marker, EndMarker::Valid);
+}
+
+#[test]
+fn test_decoding_rows_of_incomplete_image() {
+  let source = include_bytes!("./incomplete_rgb.qoi");
+  let mut decoder = Decoder::new(source.as_slice()).expect("Failed to decode header");
+  let mut row = vec![0; decoder.row_len()];
+
+  assert!(decoder.next_row(&mut row).is_err());
+}
@@ -1,4 +1,24 @@
-use qoi::{encode_image, Colorspace, ImageMeta};
+use std::cell::RefCell;
+use std::io::{self, Cursor, Read, Write};
+use std::ops::ControlFlow;
+
+use qoi::{
+  decode_image_with,
+  decode_to_vec,
+  encode_image,
+  encode_image_seekable,
+  encode_image_with,
+  encode_to_slice,
+  encode_to_slice_with,
+  encode_to_vec,
+  encode_to_vec_with,
+  Colorspace,
+  DecodeOptions,
+  EncodeOptions,
+  Error,
+  ImageMeta,
+  Progress,
+};
 
 #[test]
 fn test_encoding_blank_image() {
@@ -59,3 +79,307 @@ fn compare_encoded_image_to_reference() {
     dest.as_slice(),
   );
 }
+
+#[test]
+fn compare_encoded_rgb_image_to_reference() {
+  let source = include_bytes!("./testcard_rgb_256x256.bin");
+  let mut dest = Vec::new();
+
+  let meta = ImageMeta {
+    width: 256,
+    height: 256,
+    channels: 3,
+    colorspace: Colorspace::Linear,
+  };
+
+  let result = encode_image(&mut source.as_slice(), &mut dest, &meta);
+
+  assert!(result.is_ok());
+  assert_eq!(
+    include_bytes!("./testcard_rgb_linear_256x256.qoi").as_slice(),
+    dest.as_slice(),
+  );
+}
+
+#[test]
+fn test_encoding_image_with_invalid_channels() {
+  let source = [101, 102];
+  let mut dest = Vec::new();
+
+  let result = encode_image(
+    source.as_slice(),
+    &mut dest,
+    &ImageMeta {
+      width: 1,
+      height: 1,
+      channels: 2,
+      colorspace: Colorspace::Srgb,
+    },
+  );
+
+  assert!(result.is_err());
+  assert_eq!(dest.len(), 0);
+}
+
+#[test]
+fn compare_encoded_slice_to_reference() {
+  let source = include_bytes!("./testcard_rgba_256x256.bin");
+  let expected = include_bytes!("./testcard_rgba_256x256.qoi");
+
+  let meta = ImageMeta {
+    width: 256,
+    height: 256,
+    channels: 4,
+    colorspace: Colorspace::Srgb,
+  };
+
+  let mut dest = vec![0; meta.max_encoded_len().unwrap()];
+  let len = encode_to_slice(source, &mut dest, &meta).expect("Failed to encode image");
+
+  assert_eq!(expected.as_slice(), &dest[..len]);
+  assert_eq!(expected.as_slice(), encode_to_vec(source, &meta).unwrap().as_slice());
+}
+
+#[test]
+fn test_encoding_into_small_slice() {
+  let source = [0, 0, 0, 255];
+  let mut dest = [0; 8];
+
+  let meta = ImageMeta {
+    width: 1,
+    height: 1,
+    channels: 4,
+    colorspace: Colorspace::Srgb,
+  };
+
+  let result = encode_to_slice(&source, &mut dest, &meta);
+
+  assert!(matches!(result, Err(Error::BufferTooSmall(27))));
+}
+
+#[test]
+fn test_encoding_into_slice_shorter_than_max_encoded_len() {
+  let source = include_bytes!("./testcard_rgba_256x256.bin");
+  let expected = include_bytes!("./testcard_rgba_256x256.qoi");
+
+  let meta = ImageMeta {
+    width: 256,
+    height: 256,
+    channels: 4,
+    colorspace: Colorspace::Srgb,
+  };
+
+  let mut dest = vec![0; expected.len()];
+  let len = encode_to_slice(source, &mut dest, &meta).expect("Failed to encode image");
+
+  assert_eq!(expected.as_slice(), &dest[..len]);
+
+  let max_len = meta.max_encoded_len().unwrap();
+  let result = encode_to_slice(source, &mut dest[..expected.len() - 1], &meta);
+
+  assert!(matches!(result, Err(Error::BufferTooSmall(len)) if len == max_len));
+}
+
+#[test]
+fn test_encoding_incomplete_slice() {
+  let source = [0, 0, 0, 255];
+
+  let meta = ImageMeta {
+    width: 2,
+    height: 1,
+    channels: 4,
+    colorspace: Colorspace::Srgb,
+  };
+
+  assert!(matches!(encode_to_vec(&source, &meta), Err(Error::UnexpectedEof(None))));
+}
+
+#[test]
+fn test_encoding_incomplete_image() {
+  let source = [0, 0, 0, 255];
+
+  let meta = ImageMeta {
+    width: 1,
+    height: 2,
+    channels: 4,
+    colorspace: Colorspace::Srgb,
+  };
+
+  let result = encode_image(source.as_slice(), io::sink(), &meta);
+  assert!(matches!(result, Err(Error::UnexpectedEof(None))));
+
+  let options = EncodeOptions { flip_vertically: true, ..Default::default() };
+  let result = encode_image_seekable(Cursor::new(source), io::sink(), &meta, &options);
+  assert!(matches!(result, Err(Error::UnexpectedEof(None))));
+}
+
+#[test]
+fn test_encoding_premultiplied_pixels() {
+  let source = [128, 128, 128, 128, 10, 20, 30, 0, 40, 50, 60, 255, 200, 100, 50, 100];
+  let options = EncodeOptions { unpremultiply: true, ..Default::default() };
+
+  let meta = ImageMeta {
+    width: 4,
+    height: 1,
+    channels: 4,
+    colorspace: Colorspace::Srgb,
+  };
+
+  let expected = [255, 255, 255, 128, 0, 0, 0, 0, 40, 50, 60, 255, 255, 255, 128, 100];
+
+  let mut from_reader = Vec::new();
+  encode_image_with(source.as_slice(), &mut from_reader, &meta, &options).unwrap();
+
+  let from_vec = encode_to_vec_with(&source, &meta, &options).unwrap();
+
+  let mut from_slice = vec![0; meta.max_encoded_len().unwrap()];
+  let len = encode_to_slice_with(&source, &mut from_slice, &meta, &options).unwrap();
+  from_slice.truncate(len);
+
+  assert_eq!(from_reader, from_vec);
+  assert_eq!(from_reader, from_slice);
+  assert_eq!(decode_to_vec(&from_reader).unwrap().1, expected);
+}
+
+#[test]
+fn test_round_tripping_premultiplied_image() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let options = DecodeOptions { premultiply: true, ..Default::default() };
+  let mut premultiplied = Vec::new();
+
+  let report = decode_image_with(source.as_slice(), &mut premultiplied, &options).unwrap();
+  let encode_options = EncodeOptions { unpremultiply: true, ..Default::default() };
+  let encoded = encode_to_vec_with(&premultiplied, &report.meta, &encode_options).unwrap();
+  let mut dest = Vec::new();
+
+  decode_image_with(encoded.as_slice(), &mut dest, &options).unwrap();
+
+  assert_eq!(dest, premultiplied);
+}
+
+#[test]
+fn test_encoding_flipped_image() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let (meta, pixels) = decode_to_vec(source).unwrap();
+  let flipped: Vec<u8> = pixels.chunks(256 * 4).rev().flatten().copied().collect();
+  let options = EncodeOptions { flip_vertically: true, ..Default::default() };
+
+  let mut from_reader = Vec::new();
+  let mut reader = Cursor::new(flipped.as_slice());
+  encode_image_seekable(&mut reader, &mut from_reader, &meta, &options).unwrap();
+
+  assert_eq!(reader.position(), flipped.len() as u64);
+  assert_eq!(from_reader, source);
+  assert_eq!(encode_to_vec_with(&flipped, &meta, &options).unwrap(), source);
+}
+
+#[test]
+fn test_encoding_with_unbuffered_io() {
+  struct Counting<T> {
+    inner: T,
+    calls: usize,
+  }
+
+  impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+      self.calls += 1;
+      self.inner.read(buf)
+    }
+  }
+
+  impl<W: Write> Write for Counting<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+      self.calls += 1;
+      self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+      self.inner.flush()
+    }
+  }
+
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let (meta, mut pixels) = decode_to_vec(source).unwrap();
+  let pixels_len = pixels.len() as u64;
+
+  // Trailing data after the pixel data isn't read.
+  pixels.extend_from_slice(&[1, 2, 3]);
+
+  let mut reader = Counting { inner: Cursor::new(pixels), calls: 0 };
+  let mut writer = Counting { inner: Vec::new(), calls: 0 };
+
+  encode_image(&mut reader, &mut writer, &meta).unwrap();
+
+  assert_eq!(writer.inner, source);
+  assert_eq!(reader.inner.position(), pixels_len);
+  assert!(reader.calls < 64, "{} reads", reader.calls);
+  assert!(writer.calls < 64, "{} writes", writer.calls);
+}
+
+#[test]
+fn test_reporting_encode_progress() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let (meta, pixels) = decode_to_vec(source).unwrap();
+  let calls = RefCell::new(Vec::new());
+
+  let callback = |rows_done, rows_total| {
+    calls.borrow_mut().push((rows_done, rows_total));
+    ControlFlow::Continue(())
+  };
+
+  let options = EncodeOptions {
+    progress: Some(Progress { callback: &callback, interval: 100 }),
+    ..Default::default()
+  };
+
+  let mut dest = Vec::new();
+  encode_image_with(pixels.as_slice(), &mut dest, &meta, &options).unwrap();
+
+  // Pixels are read in chunks, so progress is reported at the first chunk
+  // boundary past each interval.
+  let reported = calls.borrow().clone();
+  assert_eq!(dest, source);
+  assert_eq!(reported.len(), 3);
+  assert!(reported.windows(2).all(|calls| calls[0].0 < calls[1].0));
+  assert!(reported[0].0 >= 100);
+  assert_eq!(reported.last(), Some(&(256, 256)));
+
+  // Rows are read one at a time when flipping.
+  let options = EncodeOptions { flip_vertically: true, ..options };
+  calls.borrow_mut().clear();
+
+  encode_image_seekable(Cursor::new(&pixels), io::sink(), &meta, &options).unwrap();
+
+  assert_eq!(*calls.borrow(), [(100, 256), (200, 256), (256, 256)]);
+
+  calls.borrow_mut().clear();
+  encode_to_vec_with(&pixels, &meta, &options).unwrap();
+
+  assert_eq!(*calls.borrow(), [(100, 256), (200, 256), (256, 256)]);
+}
+
+#[test]
+fn test_cancelling_encode() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let (meta, pixels) = decode_to_vec(source).unwrap();
+  let callback = |_, _| ControlFlow::Break(());
+
+  let options = EncodeOptions {
+    progress: Some(Progress { callback: &callback, interval: 1 }),
+    ..Default::default()
+  };
+
+  let result = encode_image_with(pixels.as_slice(), io::sink(), &meta, &options);
+
+  assert!(matches!(result, Err(Error::Cancelled)));
+}
+
+#[test]
+fn test_encoding_with_unsupported_options() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let (meta, pixels) = decode_to_vec(source).unwrap();
+  let options = EncodeOptions { flip_vertically: true, ..Default::default() };
+  let result = encode_image_with(pixels.as_slice(), io::sink(), &meta, &options);
+
+  assert!(matches!(result, Err(Error::UnsupportedOption("flip_vertically"))));
+}
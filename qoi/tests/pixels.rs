@@ -0,0 +1,62 @@
+use qoi::{decode_pixels, decode_pixels_with, DecodeLimits, DecodeOptions, Error, Pixel, Recovery};
+
+#[test]
+fn compare_decoded_pixels_to_reference() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let reference = include_bytes!("./testcard_rgba_256x256.bin");
+  let (meta, pixels) = decode_pixels(source.as_slice()).expect("Failed to decode header");
+
+  assert_eq!(meta.num_pixels(), 256 * 256);
+
+  let pixels: Vec<Pixel> = pixels
+    .collect::<Result<_, _>>()
+    .expect("Failed to decode pixels");
+
+  let expected: Vec<Pixel> = reference
+    .chunks(4)
+    .map(|p| Pixel { r: p[0], g: p[1], b: p[2], a: p[3] })
+    .collect();
+
+  assert_eq!(pixels, expected);
+}
+
+#[test]
+fn test_decoding_pixels_of_rgb_image() {
+  let source = include_bytes!("./testcard_rgb_256x256.qoi");
+  let (meta, mut pixels) = decode_pixels(source.as_slice()).expect("Failed to decode header");
+
+  assert_eq!(meta.channels, 3);
+  assert!(pixels.all(|pixel| matches!(pixel, Ok(Pixel { a: 255, .. }))));
+}
+
+#[test]
+fn test_decoding_pixels_of_incomplete_image() {
+  let source = include_bytes!("./incomplete_rgb.qoi");
+  let (_, mut pixels) = decode_pixels(source.as_slice()).expect("Failed to decode header");
+
+  assert!(matches!(pixels.next(), Some(Err(Error::UnexpectedEof(_)))));
+  assert!(pixels.next().is_none());
+}
+
+#[test]
+fn test_decoding_pixels_with_options() {
+  let source = include_bytes!("./incomplete_rgb.qoi");
+  let fill = Pixel { r: 255, g: 0, b: 255, a: 255 };
+  let options = DecodeOptions { recovery: Some(Recovery::Fill(fill)), ..Default::default() };
+  let (meta, pixels) = decode_pixels_with(source.as_slice(), &options)
+    .expect("Failed to decode header");
+
+  let pixels: Vec<Pixel> = pixels.collect::<Result<_, _>>().expect("Failed to recover pixels");
+  assert_eq!(pixels.len() as u64, meta.num_pixels());
+  assert_eq!(pixels.last(), Some(&fill));
+
+  let limits = DecodeLimits { max_width: Some(meta.width - 1), ..Default::default() };
+  let options = DecodeOptions { limits, ..Default::default() };
+  assert!(matches!(decode_pixels_with(source.as_slice(), &options), Err(Error::LimitExceeded)));
+
+  let options = DecodeOptions { strict: true, ..Default::default() };
+  assert!(matches!(
+    decode_pixels_with(source.as_slice(), &options),
+    Err(Error::UnsupportedOption("strict"))
+  ));
+}
@@ -0,0 +1,126 @@
+use qoi::{
+  decode_planar,
+  decode_planar_to_slices,
+  decode_planar_to_slices_with,
+  decode_planar_with,
+  decode_to_vec_with,
+  DecodeLimits,
+  DecodeOptions,
+  Error,
+  Pixel,
+  PixelFormat,
+};
+
+// Splits interleaved pixels with the given number of channels into planes,
+// with an opaque alpha plane appended to pixels without alpha.
+fn split(pixels: &[u8], channels: usize, planes: usize) -> Vec<Vec<u8>> {
+  (0..planes)
+    .map(|c| pixels.chunks(channels).map(|p| p.get(c).copied().unwrap_or(255)).collect())
+    .collect()
+}
+
+#[test]
+fn compare_planar_image_to_reference() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let reference = include_bytes!("./testcard_rgba_256x256.bin");
+
+  let mut planes = vec![Vec::new(); 4];
+  let meta = decode_planar(source.as_slice(), &mut planes).expect("Failed to decode image");
+
+  assert_eq!(meta.channels, 4);
+  assert_eq!(planes, split(reference, 4, 4));
+}
+
+#[test]
+fn test_compositing_planes_onto_background() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let mut planes = vec![Vec::new(); 3];
+
+  // Alpha isn't dropped silently.
+  assert_eq!(decode_planar(source.as_slice(), &mut planes), Err(Error::MissingBackground));
+
+  let options = DecodeOptions {
+    background: Some(Pixel { r: 200, g: 100, b: 50, a: 255 }),
+    ..Default::default()
+  };
+  let rgb = DecodeOptions { format: Some(PixelFormat::Rgb), ..options.clone() };
+  let (_, expected) = decode_to_vec_with(source, &rgb).expect("Failed to decode image");
+
+  let mut planes = vec![Vec::new(); 3];
+  decode_planar_with(source.as_slice(), &mut planes, &options).expect("Failed to decode image");
+  assert_eq!(planes, split(&expected, 3, 3));
+
+  let mut chw = vec![0; 256 * 256 * 3];
+  let mut planes: Vec<&mut [u8]> = chw.chunks_exact_mut(256 * 256).collect();
+  decode_planar_to_slices_with(source, &mut planes, &options).expect("Failed to decode image");
+  assert_eq!(chw, split(&expected, 3, 3).concat());
+}
+
+#[test]
+fn test_decoding_rgb_image_to_alpha_plane() {
+  let source = include_bytes!("./testcard_rgb_256x256.qoi");
+  let reference = include_bytes!("./testcard_rgb_256x256.bin");
+  let mut planes = vec![Vec::new(); 4];
+
+  decode_planar(source.as_slice(), &mut planes).expect("Failed to decode image");
+
+  assert_eq!(planes, split(reference, 3, 4));
+}
+
+#[test]
+fn compare_planar_slices_to_reference() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let reference = include_bytes!("./testcard_rgba_256x256.bin");
+  let mut chw = vec![0; 256 * 256 * 4];
+  let mut planes: Vec<&mut [u8]> = chw.chunks_exact_mut(256 * 256).collect();
+
+  decode_planar_to_slices(source, &mut planes).expect("Failed to decode image");
+
+  assert_eq!(chw, split(reference, 4, 4).concat());
+}
+
+#[test]
+fn test_decoding_invalid_number_of_planes() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let mut planes = vec![Vec::new(); 2];
+
+  assert_eq!(decode_planar(source.as_slice(), &mut planes), Err(Error::InvalidChannels(2)));
+  assert_eq!(decode_planar_to_slices(source, &mut []), Err(Error::InvalidChannels(0)));
+}
+
+#[test]
+fn test_decoding_into_small_planes() {
+  let source = include_bytes!("./testcard_rgb_256x256.qoi");
+  let mut chw = vec![0; 256 * 256 * 3 - 1];
+  let (r, gb) = chw.split_at_mut(256 * 256);
+  let (g, b) = gb.split_at_mut(256 * 256);
+
+  assert_eq!(
+    decode_planar_to_slices(source, &mut [r, g, b]),
+    Err(Error::BufferTooSmall(256 * 256))
+  );
+}
+
+#[test]
+fn test_decoding_planes_with_options() {
+  let source = include_bytes!("./testcard_rgb_256x256.qoi");
+  let mut planes = vec![Vec::new(); 3];
+
+  let limits = DecodeLimits { max_bytes: Some(1024), ..Default::default() };
+  let options = DecodeOptions { limits, ..Default::default() };
+  assert_eq!(
+    decode_planar_with(source.as_slice(), &mut planes, &options),
+    Err(Error::LimitExceeded)
+  );
+
+  let options = DecodeOptions { strict: true, ..Default::default() };
+  decode_planar_with(source.as_slice(), &mut planes, &options).expect("Failed to decode image");
+  assert_eq!(planes.concat().len(), 3 * 256 * 256);
+
+  let mut trailing = source.to_vec();
+  trailing.push(0);
+  assert!(matches!(
+    decode_planar_with(trailing.as_slice(), &mut vec![Vec::new(); 3], &options),
+    Err(Error::TrailingData(_))
+  ));
+}
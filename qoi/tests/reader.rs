@@ -0,0 +1,70 @@
+use std::io::{self, Read};
+
+use qoi::{DecodeOptions, Decoder, DecoderReader, PixelFormat};
+
+#[test]
+fn compare_read_image_to_reference() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let mut reader = DecoderReader::new(source.as_slice()).expect("Failed to decode header");
+  let mut dest = Vec::new();
+
+  reader.read_to_end(&mut dest).expect("Failed to read image");
+
+  assert_eq!(reader.meta().channels, 4);
+  assert_eq!(
+    include_bytes!("./testcard_rgba_256x256.bin").as_slice(),
+    dest.as_slice(),
+  );
+
+  assert!(reader.finish().is_ok());
+}
+
+#[test]
+fn test_reading_image_in_odd_sizes() {
+  let source = include_bytes!("./testcard_rgb_256x256.qoi");
+  let reference = include_bytes!("./testcard_rgb_256x256.bin");
+  let mut reader = Decoder::new(source.as_slice())
+    .expect("Failed to decode header")
+    .into_reader();
+
+  let mut dest = Vec::new();
+  let mut buf = [0; 7];
+  let mut sizes = [1, 2, 5, 7].into_iter().cycle();
+
+  loop {
+    let len = sizes.next().unwrap_or(1);
+    let read = reader.read(&mut buf[..len]).expect("Failed to read image");
+
+    if read == 0 {
+      break;
+    }
+
+    dest.extend_from_slice(&buf[..read]);
+  }
+
+  assert_eq!(dest.as_slice(), reference.as_slice());
+}
+
+#[test]
+fn test_reading_image_with_pixel_format() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let options = DecodeOptions { format: Some(PixelFormat::GrayAlpha), ..Default::default() };
+  let mut reader = DecoderReader::with_options(source.as_slice(), &options)
+    .expect("Failed to decode header");
+
+  let copied = io::copy(&mut reader, &mut io::sink()).expect("Failed to read image");
+  assert_eq!(copied, 256 * 256 * 2);
+}
+
+#[test]
+fn test_reading_incomplete_image() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let mut reader = DecoderReader::new(&source[..source.len() / 2])
+    .expect("Failed to decode header");
+
+  let mut dest = Vec::new();
+  let err = reader.read_to_end(&mut dest).expect_err("Expected incomplete image");
+
+  assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+  assert!(!dest.is_empty());
+}
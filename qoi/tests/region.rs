@@ -0,0 +1,119 @@
+use std::io;
+
+use qoi::{decode_region, decode_region_with, DecodeLimits, DecodeOptions, Error, PixelFormat, Rect};
+
+// Crops the reference RGBA pixels of the 256x256 testcard to the given rect.
+fn crop(rect: Rect) -> Vec<u8> {
+  let reference = include_bytes!("./testcard_rgba_256x256.bin");
+
+  (rect.y..rect.y + rect.height)
+    .flat_map(|y| {
+      let start = (y * 256 + rect.x) as usize * 4;
+      reference[start..start + rect.width as usize * 4].iter().copied()
+    })
+    .collect()
+}
+
+// A reader that counts the number of bytes read from it.
+struct CountingReader<'a> {
+  bytes: &'a [u8],
+  read: usize,
+}
+
+impl io::Read for CountingReader<'_> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let read = self.bytes.read(buf)?;
+    self.read += read;
+    Ok(read)
+  }
+}
+
+#[test]
+fn compare_decoded_regions_to_reference() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+
+  let rects = [
+    Rect { x: 0, y: 0, width: 256, height: 256 },
+    Rect { x: 64, y: 32, width: 100, height: 50 },
+    Rect { x: 0, y: 100, width: 256, height: 10 },
+    Rect { x: 255, y: 255, width: 1, height: 1 },
+  ];
+
+  for rect in rects {
+    let mut dest = Vec::new();
+    let meta = decode_region(source.as_slice(), &mut dest, rect).expect("Failed to decode region");
+
+    assert_eq!(meta.width, 256);
+    assert_eq!(dest, crop(rect), "{:?}", rect);
+  }
+}
+
+#[test]
+fn test_decoding_region_stops_reading() {
+  let mut source = include_bytes!("./testcard_rgba_256x256.qoi").to_vec();
+
+  // Pad the image so that the reader's chunks can't cover it.
+  source.resize(source.len() + 64 * 1024, 0);
+
+  let mut reader = CountingReader { bytes: &source, read: 0 };
+  let rect = Rect { x: 0, y: 0, width: 16, height: 16 };
+  let mut dest = Vec::new();
+
+  decode_region(&mut reader, &mut dest, rect).expect("Failed to decode region");
+
+  assert_eq!(dest, crop(rect));
+  assert!(reader.read < source.len() / 2);
+}
+
+#[test]
+fn test_decoding_empty_region() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let mut dest = Vec::new();
+
+  decode_region(source.as_slice(), &mut dest, Rect { x: 10, y: 10, width: 0, height: 5 })
+    .expect("Failed to decode region");
+
+  assert!(dest.is_empty());
+}
+
+#[test]
+fn test_decoding_region_outside_image() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+
+  let rects = [
+    Rect { x: 200, y: 0, width: 57, height: 1 },
+    Rect { x: 0, y: 256, width: 1, height: 1 },
+    Rect { x: u32::MAX, y: 0, width: u32::MAX, height: 1 },
+  ];
+
+  for rect in rects {
+    assert_eq!(decode_region(source.as_slice(), Vec::new(), rect), Err(Error::InvalidRect));
+  }
+}
+
+#[test]
+fn test_decoding_region_with_options() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let rect = Rect { x: 16, y: 32, width: 8, height: 4 };
+  let options = DecodeOptions { format: Some(PixelFormat::Bgra), ..Default::default() };
+  let mut dest = Vec::new();
+
+  decode_region_with(source.as_slice(), &mut dest, rect, &options)
+    .expect("Failed to decode region");
+
+  let expected: Vec<u8> = crop(rect).chunks(4).flat_map(|p| [p[2], p[1], p[0], p[3]]).collect();
+  assert_eq!(dest, expected);
+
+  let limits = DecodeLimits { max_pixels: Some(256 * 255), ..Default::default() };
+  let options = DecodeOptions { limits, ..Default::default() };
+  assert_eq!(
+    decode_region_with(source.as_slice(), Vec::new(), rect, &options),
+    Err(Error::LimitExceeded)
+  );
+
+  let options = DecodeOptions { strict: true, ..Default::default() };
+  assert_eq!(
+    decode_region_with(source.as_slice(), Vec::new(), rect, &options),
+    Err(Error::UnsupportedOption("strict"))
+  );
+}
@@ -0,0 +1,139 @@
+use qoi::{
+  decode_image,
+  Colorspace,
+  DecodeOptions,
+  EndMarker,
+  Error,
+  ImageMeta,
+  PixelFormat,
+  Recovery,
+  StreamingDecoder,
+};
+
+// Feeds the source to a new decoder in chunks of the given size, returning
+// the decoded pixels and the decoder.
+fn feed_in_chunks(
+  mut decoder: StreamingDecoder,
+  source: &[u8],
+  chunk_size: usize,
+) -> Result<(Vec<u8>, StreamingDecoder), Error> {
+  let mut dest = Vec::new();
+
+  for chunk in source.chunks(chunk_size) {
+    dest.extend_from_slice(decoder.feed(chunk)?);
+  }
+
+  Ok((dest, decoder))
+}
+
+#[test]
+fn compare_streamed_image_to_reference() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let reference = include_bytes!("./testcard_rgba_256x256.bin");
+
+  for chunk_size in [1, 2, 3, 7, 13, 4096, source.len()] {
+    let (dest, decoder) = feed_in_chunks(StreamingDecoder::new(), source, chunk_size)
+      .expect("Failed to decode image");
+
+    let report = decoder.finish().expect("Failed to finish decoding");
+
+    assert_eq!(
+      report.meta,
+      ImageMeta {
+        width: 256,
+        height: 256,
+        channels: 4,
+        colorspace: Colorspace::Srgb,
+      }
+    );
+
+    assert_eq!(report.end_marker, EndMarker::Valid);
+    assert_eq!(dest.as_slice(), reference.as_slice(), "chunk size {}", chunk_size);
+  }
+}
+
+#[test]
+fn test_streaming_image_meta() {
+  let source = include_bytes!("./testcard_rgb_256x256.qoi");
+  let mut decoder = StreamingDecoder::new();
+
+  decoder.feed(&source[..10]).expect("Failed to decode chunk");
+  assert!(decoder.meta().is_none());
+
+  decoder.feed(&source[10..20]).expect("Failed to decode chunk");
+  assert_eq!(decoder.meta().map(|meta| meta.channels), Some(3));
+}
+
+#[test]
+fn test_streaming_image_with_pixel_format() {
+  let source = include_bytes!("./testcard_rgb_256x256.qoi");
+  let options = DecodeOptions { format: Some(PixelFormat::Bgra), ..Default::default() };
+  let decoder = StreamingDecoder::with_options(&options).expect("Failed to create decoder");
+  let (dest, decoder) = feed_in_chunks(decoder, source, 100).expect("Failed to decode image");
+
+  let expected: Vec<u8> = include_bytes!("./testcard_rgb_256x256.bin")
+    .chunks(3)
+    .flat_map(|p| [p[2], p[1], p[0], 255])
+    .collect();
+
+  assert!(decoder.finish().is_ok());
+  assert_eq!(dest, expected);
+}
+
+#[test]
+fn test_finishing_incomplete_stream() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let (_, decoder) = feed_in_chunks(StreamingDecoder::new(), &source[..source.len() / 2], 100)
+    .expect("Failed to decode image");
+
+  let err = decoder.finish().expect_err("Finished an incomplete stream");
+  let expected = decode_image(&source[..source.len() / 2], Vec::new()).map(|_| ());
+
+  assert_eq!(Err(err), expected);
+}
+
+#[test]
+fn test_streaming_image_with_bad_end_marker() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let truncated = &source[..source.len() - 2];
+  let strict = DecodeOptions { strict: true, ..Default::default() };
+
+  let (_, decoder) = feed_in_chunks(StreamingDecoder::new(), truncated, 100)
+    .expect("Failed to decode image");
+
+  assert_eq!(
+    decoder.finish().map(|report| report.end_marker).ok(),
+    Some(EndMarker::Truncated(6))
+  );
+
+  let decoder = StreamingDecoder::with_options(&strict).expect("Failed to create decoder");
+  let (_, decoder) = feed_in_chunks(decoder, truncated, 100).expect("Failed to decode image");
+
+  assert!(matches!(decoder.finish(), Err(Error::UnexpectedEof(_))));
+
+  let trailing = [source.as_slice(), &[0]].concat();
+
+  let decoder = StreamingDecoder::with_options(&strict).expect("Failed to create decoder");
+
+  assert!(matches!(
+    feed_in_chunks(decoder, &trailing, 100),
+    Err(Error::TrailingData(location)) if location.offset == source.len() as u64
+  ));
+
+  let (_, decoder) = feed_in_chunks(StreamingDecoder::new(), &trailing, 100)
+    .expect("Failed to decode image");
+  let report = decoder.finish().expect("Failed to decode image");
+
+  assert_eq!(report.trailing_bytes, Some(1));
+  assert!(report.has_trailing_data);
+}
+
+#[test]
+fn test_streaming_image_with_unsupported_options() {
+  let options = DecodeOptions { recovery: Some(Recovery::Truncate), ..Default::default() };
+
+  assert!(matches!(
+    StreamingDecoder::with_options(&options),
+    Err(Error::UnsupportedOption("recovery"))
+  ));
+}
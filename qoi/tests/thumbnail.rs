@@ -0,0 +1,168 @@
+use qoi::{
+  decode_image,
+  decode_thumbnail,
+  decode_thumbnail_with,
+  encode_to_vec,
+  Colorspace,
+  DecodeLimits,
+  DecodeOptions,
+  Error,
+  ImageMeta,
+  PixelFormat,
+};
+
+// Averages each 2x2 box of the reference RGBA pixels of the 256x256
+// testcard, weighting color channels by alpha. When `linear_light` is set,
+// color channels are averaged in linear light, as for sRGB images.
+fn halve_reference(linear_light: bool) -> Vec<f64> {
+  let reference = include_bytes!("./testcard_rgba_256x256.bin");
+  let mut dest = Vec::new();
+
+  let to_linear = |c: f64| {
+    if c <= 0.04045 {
+      c / 12.92
+    } else {
+      ((c + 0.055) / 1.055).powf(2.4)
+    }
+  };
+
+  let to_srgb = |c: f64| {
+    if c <= 0.0031308 {
+      c * 12.92
+    } else {
+      1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+  };
+
+  for y in 0..128 {
+    for x in 0..128 {
+      let mut sums = [0f64; 4];
+
+      for (box_x, box_y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+        let i = ((y * 2 + box_y) * 256 + x * 2 + box_x) * 4;
+        let a = reference[i + 3] as f64;
+
+        for c in 0..3 {
+          let value = match linear_light {
+            true => to_linear(reference[i + c] as f64 / 255.0),
+            false => reference[i + c] as f64 / 255.0,
+          };
+
+          sums[c] += value * a;
+        }
+
+        sums[3] += a;
+      }
+
+      for sum in &sums[..3] {
+        let value = if sums[3] == 0.0 { 0.0 } else { sum / sums[3] };
+
+        match linear_light {
+          true => dest.push((to_srgb(value) * 255.0).round()),
+          false => dest.push((value * 255.0).round()),
+        }
+      }
+
+      dest.push((sums[3] / 4.0).round());
+    }
+  }
+
+  dest
+}
+
+#[test]
+fn compare_thumbnail_to_reference() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let mut dest = Vec::new();
+  let meta = decode_thumbnail(source.as_slice(), &mut dest, 128, 128)
+    .expect("Failed to decode thumbnail");
+
+  assert_eq!((meta.width, meta.height, meta.channels), (128, 128, 4));
+  assert_eq!(meta.colorspace, Colorspace::Srgb);
+  assert_eq!(dest.len(), 128 * 128 * 4);
+
+  for (i, (value, expected)) in dest.iter().zip(halve_reference(true)).enumerate() {
+    match i % 4 {
+      3 => assert_eq!(*value as f64, expected),
+      _ => assert!((*value as f64 - expected).abs() <= 1.0),
+    }
+  }
+}
+
+#[test]
+fn compare_linear_thumbnail_to_reference() {
+  let source = include_bytes!("./testcard_rgba_linear_256x256.qoi");
+  let mut dest = Vec::new();
+  let meta = decode_thumbnail(source.as_slice(), &mut dest, 128, 128)
+    .expect("Failed to decode thumbnail");
+
+  assert_eq!((meta.width, meta.height, meta.channels), (128, 128, 4));
+  assert_eq!(meta.colorspace, Colorspace::Linear);
+
+  let expected: Vec<u8> = halve_reference(false).into_iter().map(|value| value as u8).collect();
+  assert_eq!(dest, expected);
+}
+
+#[test]
+fn test_thumbnail_of_small_image() {
+  let source = include_bytes!("./testcard_rgb_256x256.qoi");
+  let mut dest = Vec::new();
+  let mut expected = Vec::new();
+
+  let meta = decode_thumbnail(source.as_slice(), &mut dest, 1000, 256)
+    .expect("Failed to decode thumbnail");
+
+  decode_image(source.as_slice(), &mut expected).expect("Failed to decode image");
+
+  assert_eq!((meta.width, meta.height, meta.channels), (256, 256, 3));
+  assert_eq!(dest, expected);
+}
+
+#[test]
+fn test_thumbnail_preserves_aspect_ratio() {
+  let meta = ImageMeta { width: 300, height: 100, channels: 3, colorspace: Colorspace::Srgb };
+  let pixels: Vec<u8> = (0..300 * 100).flat_map(|_| [10, 20, 30]).collect();
+  let source = encode_to_vec(&pixels, &meta).expect("Failed to encode image");
+
+  for (max_width, max_height, expected) in [(64, 64, (64, 21)), (90, 10, (30, 10)), (1, 1, (1, 1))] {
+    let mut dest = Vec::new();
+    let thumbnail = decode_thumbnail(source.as_slice(), &mut dest, max_width, max_height)
+      .expect("Failed to decode thumbnail");
+
+    assert_eq!((thumbnail.width, thumbnail.height), expected);
+    assert_eq!(dest.len(), (expected.0 * expected.1 * 3) as usize);
+    assert!(dest.chunks(3).all(|p| p == [10, 20, 30]));
+  }
+}
+
+#[test]
+fn test_thumbnail_with_zero_bounds() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+
+  assert_eq!(
+    decode_thumbnail(source.as_slice(), Vec::new(), 0, 64),
+    Err(Error::InvalidDimensions)
+  );
+}
+
+#[test]
+fn test_decoding_thumbnail_with_options() {
+  let source = include_bytes!("./testcard_rgba_256x256.qoi");
+  let options = DecodeOptions { format: Some(PixelFormat::Bgra), ..Default::default() };
+  let mut expected = Vec::new();
+  let mut dest = Vec::new();
+
+  decode_thumbnail(source.as_slice(), &mut expected, 32, 32).expect("Failed to decode thumbnail");
+  decode_thumbnail_with(source.as_slice(), &mut dest, 32, 32, &options)
+    .expect("Failed to decode thumbnail");
+
+  let expected: Vec<u8> = expected.chunks(4).flat_map(|p| [p[2], p[1], p[0], p[3]]).collect();
+  assert_eq!(dest, expected);
+
+  let limits = DecodeLimits { max_height: Some(255), ..Default::default() };
+  let options = DecodeOptions { limits, ..Default::default() };
+  assert_eq!(
+    decode_thumbnail_with(source.as_slice(), Vec::new(), 32, 32, &options),
+    Err(Error::LimitExceeded)
+  );
+}
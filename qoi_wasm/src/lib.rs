@@ -114,10 +114,12 @@
 //! // channels, and colorspace.
 //! let imageWidth = 100;
 //! let imageHeight = 100;
+//! let channels = 4; // Or 3 for RGB.
 //! let colorspace = 1; // Or 0 for Srgb.
 //! wasm.instance.exports.qoi_image_encode(
 //!   imageWidth,
 //!   imageHeight,
+//!   channels,
 //!   colorspace,
 //!   pointer,
 //!   size,
@@ -130,8 +132,9 @@
 //! 
 
 use std::mem;
+use std::slice;
 
-use qoi::{decode_image, encode_image, Colorspace, Error, ImageMeta};
+use qoi::{decode_to_vec, encode_image, is_qoi, Colorspace, Error, ImageMeta};
 
 // Maps a QOI crate error into an integer that can be trivially passed through
 // the WASM FFI boundary.
@@ -142,13 +145,23 @@ struct ErrorCode {
 impl From<Error> for ErrorCode {
   fn from(error: Error) -> Self {
     match error {
+      Error::BufferTooSmall(_) => ErrorCode { code: 12 },
+      Error::Cancelled => ErrorCode { code: 18 },
+      Error::InvalidChannels(_) => ErrorCode { code: 11 },
       Error::InvalidColorspace(_) => ErrorCode { code: 1 },
       Error::InvalidDimensions => ErrorCode { code: 2 },
+      Error::InvalidEndMarker(_) => ErrorCode { code: 14 },
       Error::InvalidHeader => ErrorCode { code: 3 },
-      Error::InvalidIndex(_) => ErrorCode { code: 4 },
+      Error::InvalidIndex(..) => ErrorCode { code: 4 },
+      Error::InvalidRect => ErrorCode { code: 16 },
+      Error::InvalidStride => ErrorCode { code: 17 },
       Error::IoError(_) => ErrorCode { code: 5 },
-      Error::UnexpectedEof => ErrorCode { code: 6 },
-      Error::UnknownTag(_) => ErrorCode { code: 7 },
+      Error::LimitExceeded => ErrorCode { code: 13 },
+      Error::MissingBackground => ErrorCode { code: 20 },
+      Error::TrailingData(_) => ErrorCode { code: 15 },
+      Error::UnexpectedEof(_) => ErrorCode { code: 6 },
+      Error::UnknownTag(..) => ErrorCode { code: 7 },
+      Error::UnsupportedOption(_) => ErrorCode { code: 19 },
     }
   }
 }
@@ -204,6 +217,7 @@ pub unsafe extern "C" fn qoi_dealloc(ptr: *mut u8, size: usize) {
 pub unsafe extern "C" fn qoi_image_encode(
   width: u32,
   height: u32,
+  channels: u8,
   colorspace: u8,
   buf_ptr: *mut u8,
   buf_size: usize,
@@ -216,11 +230,6 @@ pub unsafe extern "C" fn qoi_image_encode(
     }
   };
 
-  let channels = match colorspace {
-    Colorspace::Linear => 3,
-    Colorspace::Srgb => 4,
-  };
-
   let image_meta = ImageMeta { channels, colorspace, height, width };
   let source = Vec::from_raw_parts(buf_ptr, buf_size, buf_size);
   let mut dest = Vec::new();
@@ -258,10 +267,9 @@ pub unsafe extern "C" fn qoi_image_encode(
 #[no_mangle]
 pub unsafe extern "C" fn qoi_image_decode(buf_ptr: *mut u8, buf_size: usize) {
   let source = Vec::from_raw_parts(buf_ptr, buf_size, buf_size);
-  let mut dest = Vec::new();
 
-  match decode_image(&mut source.as_slice(), &mut dest) {
-    Ok(image_meta) => {
+  match decode_to_vec(&source) {
+    Ok((image_meta, mut dest)) => {
       let ImageMeta { channels, colorspace, height, width } = image_meta;
 
       let ptr = dest.as_mut_ptr();
@@ -276,3 +284,19 @@ pub unsafe extern "C" fn qoi_image_decode(buf_ptr: *mut u8, buf_size: usize) {
     }
   }
 }
+
+/// Takes a `buf_ptr` and `buf_size` to a chunk of memory and checks if it
+/// starts with a valid QOI image header, see `qoi::is_qoi`.
+/// 
+/// Unlike decoding, this doesn't take ownership of the memory, so it still
+/// needs to be deallocated with `qoi_dealloc` afterwards.
+/// 
+/// # Safety
+/// 
+/// Requires reading `buf_size` bytes of raw memory created by calling
+/// `qoi_malloc`. Providing an invalid `buf_ptr` or `buf_size` will result in
+/// reading invalid memory and likely crash the WASM instance.
+#[no_mangle]
+pub unsafe extern "C" fn qoi_is_qoi(buf_ptr: *const u8, buf_size: usize) -> bool {
+  is_qoi(slice::from_raw_parts(buf_ptr, buf_size))
+}
@@ -35,6 +35,36 @@ globalThis.ErrorCode = class ErrorCode extends Error {
 
     // Failed to decode native image and get `ImageData`
     10: 'Failed to get `ImageData` from image',
+
+    // Error::InvalidChannels(_)
+    11: 'Invalid number of image channels',
+
+    // Error::BufferTooSmall(_)
+    12: 'Buffer is too small to hold the decoded image',
+
+    // Error::LimitExceeded
+    13: 'Image exceeds the decode limits',
+
+    // Error::InvalidEndMarker
+    14: 'Invalid QOI image end marker',
+
+    // Error::TrailingData
+    15: 'Unexpected data after the QOI image end marker',
+
+    // Error::InvalidRect
+    16: 'Rectangle is not within the image',
+
+    // Error::InvalidStride
+    17: 'Stride is shorter than a row of pixels',
+
+    // Error::Cancelled
+    18: 'Decoding or encoding was cancelled',
+
+    // Error::UnsupportedOption(_)
+    19: 'Option is not supported by this function',
+
+    // Error::MissingBackground
+    20: 'A background color is required to drop the image\'s alpha',
   };
 
   /** @type {keyof ErrorCode.codes} */
@@ -206,10 +206,10 @@ async function onInputChange({ event, update, worker }) {
  * @returns {Promise<ViewProps>}
  */
 async function processImageFile(file, worker) {
-  let fileBuffer = await file.arrayBuffer();
+  let { isQoi, buffer: fileBuffer } = await qoiCheckImage(worker, await file.arrayBuffer());
   let fileName = file.name.split('.').slice(0, -1).join('.');
 
-  if (isQoiImage(fileBuffer)) {
+  if (isQoi) {
     let imageData = await qoiDecodeImage(worker, fileBuffer);
     let blob = await blobFromImageData(imageData);
     let url = URL.createObjectURL(blob);
@@ -239,6 +239,42 @@ async function processImageFile(file, worker) {
   };
 }
 
+/**
+ * Checks if the image buffer starts with a valid QOI image header. The buffer
+ * is transferred to the worker and back, so the returned buffer must be used
+ * in its place.
+ *
+ * @param {Worker} worker
+ * @param {ArrayBuffer} buffer
+ *
+ * @returns {Promise<{ isQoi: boolean; buffer: ArrayBuffer }>}
+ */
+function qoiCheckImage(worker, buffer) {
+  return new Promise((resolve, reject) => {
+    worker.addEventListener(
+      'message',
+
+      /** @param {MessageEvent<IsQoiCompleteMessage|ErrorMessage>} e */
+      (e) => {
+        if (e.data.type === 'isQoiComplete') {
+          return resolve({ isQoi: e.data.isQoi, buffer: e.data.buffer });
+        }
+
+        if (e.data.type === 'error') {
+          return reject(e.data.err);
+        }
+
+        reject(new globalThis.ErrorCode(8));
+        console.error('Unknown worker message: ', e.data);
+      },
+
+      { once: true },
+    );
+
+    worker.postMessage({ type: 'isQoi', buffer }, [buffer]);
+  });
+}
+
 /**
  * Decodes the QOI encoded image buffer into an `ImageData` instance.
  *
@@ -386,50 +422,6 @@ function blobFromImageData(imageData, type = 'image/png', quality = 1) {
   });
 }
 
-/**
- * Unicode code points for the string "qoif" as bytes.
- */
-const qoiBytesMagic = [
-  'q'.codePointAt(0),
-  'o'.codePointAt(0),
-  'i'.codePointAt(0),
-  'f'.codePointAt(0),
-];
-
-/**
- * QOI end marker bytes.
- */
-const qoiBytesEnd = [0, 0, 0, 0, 0, 0, 0, 1];
-
-/**
- * Determines if `buffer` represents a QOI encoded image by asserting that the
- * first four bytes make up the magic bytes "qoif", and that the last eight
- * bytes match the QOI end marker.
- *
- * @param {ArrayBuffer} buffer
- *
- * @returns {boolean}
- */
-function isQoiImage(buffer) {
-  let magicBytesSlice = new Uint8Array(buffer, 0, qoiBytesMagic.length);
-
-  for (let [ index, magicByte ] of qoiBytesMagic.entries()) {
-    if (magicBytesSlice[index] !== magicByte) {
-      return false;
-    }
-  }
-
-  let endMarkerSlice = new Uint8Array(buffer, buffer.byteLength - qoiBytesEnd.length);
-
-  for (let [ index, endByte ] of qoiBytesEnd.entries()) {
-    if (endMarkerSlice[index] !== endByte) {
-      return false;
-    }
-  }
-
-  return true;
-}
-
 /**
  * @param {number} numBytes
  * @param {number} [precision]
@@ -69,13 +69,21 @@ globalThis.addEventListener(
 // -- Functions ---------------------------------------------------------------
 
 /**
- * @param {MessageEvent<DecodeMessage|EncodeMessage>} e
+ * @param {MessageEvent<IsQoiMessage|DecodeMessage|EncodeMessage>} e
  * @param {WebAssembly.WebAssemblyInstantiatedSource} wasm
  *
  * @returns {Promise<void>}
  */
 async function onMessage(e, wasm) {
   try {
+    if (e.data?.type === 'isQoi') {
+      let buffer = e.data.buffer;
+      let isQoi = checkIsQoi(new Uint8Array(buffer), wasm);
+
+      // Hand the buffer back, so it can be decoded without being copied.
+      globalThis.postMessage({ type: 'isQoiComplete', isQoi, buffer }, [buffer]);
+    }
+
     if (e.data?.type === 'decode') {
       let imageData = await decode(new Uint8Array(e.data.buffer), wasm);
       let transfer = [imageData.data.buffer];
@@ -94,6 +102,28 @@ async function onMessage(e, wasm) {
   }
 }
 
+/**
+ * Checks if `data` starts with a valid QOI image header.
+ *
+ * @param {Uint8Array} data
+ * @param {WebAssembly.WebAssemblyInstantiatedSource} wasm
+ *
+ * @returns {boolean}
+ */
+function checkIsQoi(data, wasm) {
+  let {
+    qoi_dealloc,
+    qoi_is_qoi,
+  } = /** @type {WasmExports} */ (wasm.instance.exports);
+
+  let copyPointer = copyIntoWasm(data, wasm);
+  let isQoi = qoi_is_qoi(copyPointer, data.byteLength);
+
+  qoi_dealloc(copyPointer, data.byteLength);
+
+  return Boolean(isQoi);
+}
+
 /**
  * @param {Uint8Array} data
  * @param {WebAssembly.WebAssemblyInstantiatedSource} wasm 
@@ -130,7 +160,7 @@ async function onMessage(e, wasm) {
         qoi_dealloc(pointer, length);
 
         let imageData = new ImageData(
-          new Uint8ClampedArray(buffer),
+          toRgba(new Uint8Array(buffer), channels),
           width,
           height,
         );
@@ -221,9 +251,13 @@ function encode(imageData, wasm) {
       colorspace = 1;
     }
 
+    // `ImageData` pixels are always RGBA.
+    let channels = 4;
+
     qoi_image_encode(
       imageData.width,
       imageData.height,
+      channels,
       colorspace,
       copyPointer,
       imageData.data.byteLength,
@@ -231,6 +265,32 @@ function encode(imageData, wasm) {
   });
 }
 
+/**
+ * Converts decoded pixel data with the given number of `channels` into RGBA
+ * pixel data, as required by `ImageData`. RGB pixels are made fully opaque.
+ *
+ * @param {Uint8Array} pixels
+ * @param {number} channels
+ *
+ * @returns {Uint8ClampedArray}
+ */
+function toRgba(pixels, channels) {
+  if (channels === 4) {
+    return new Uint8ClampedArray(pixels.buffer);
+  }
+
+  let rgba = new Uint8ClampedArray((pixels.length / channels) * 4);
+
+  for (let i = 0, j = 0; i < pixels.length; i += channels, j += 4) {
+    rgba[j] = pixels[i];
+    rgba[j + 1] = pixels[i + 1];
+    rgba[j + 2] = pixels[i + 2];
+    rgba[j + 3] = 255;
+  }
+
+  return rgba;
+}
+
 /**
  * Copies bytes out of the `wasm` instance's memory buffer starting at
  * `pointer` up to `length`.
@@ -292,10 +352,12 @@ function encode(imageData, wasm) {
  *   qoi_image_encode: (
  *     width: number,
  *     height: number,
+ *     channels: number,
  *     colorspace: number,
  *     pointer: number,
  *     size: number,
  *   ) => void;
+ *   qoi_is_qoi: (pointer: number, size: number) => number;
  *   qoi_malloc: (size: number) => number;
  * }} WasmExports
  */
@@ -324,7 +386,9 @@ function encode(imageData, wasm) {
 /** @typedef {{ type: "load" }} LoadMessage */
 /** @typedef {{ type: "ready" }} ReadyMessage */
 /** @typedef {{ type: "error"; err: Error; }} ErrorMessage */
+/** @typedef {{ type: "isQoi"; buffer: ArrayBuffer }} IsQoiMessage */
 /** @typedef {{ type: "decode"; buffer: ArrayBuffer }} DecodeMessage */
 /** @typedef {{ type: "encode"; imageData: ImageData }} EncodeMessage */
+/** @typedef {{ type: "isQoiComplete"; isQoi: boolean; buffer: ArrayBuffer; }} IsQoiCompleteMessage */
 /** @typedef {{ type: "decodeComplete"; imageData: ImageData; }} DecodeCompleteMessage */
 /** @typedef {{ type: "encodeComplete"; buffer: ArrayBuffer; }} EncodeCompleteMessage */